@@ -1,8 +1,7 @@
-mod regexp;
-use regexp::Re;
+use regexp_parsing::regexp::Re;
 
 fn main() {
 	let regexp = Re::parse_regexp("(a|b)*a").unwrap();
 	regexp.debug_print();
-	println!("");
-}
\ No newline at end of file
+	println!();
+}
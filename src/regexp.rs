@@ -1,6 +1,38 @@
 #![allow(unused, dead_code)]
-use std::rc::Rc;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+use core::str::FromStr;
+mod analysis;
+mod arena;
 mod automaton;
+mod builder;
+mod export;
+mod glob;
+mod json;
+mod lex;
+mod lint;
+mod matcher;
+mod mermaid;
+mod multi;
+mod pike;
+mod simplify;
+mod spans;
+mod transform;
+
+pub use arena::{parse_into_arena, ArenaNode, NodeId, ReArena};
+pub use automaton::{Dfa, Nfa};
+pub use lex::{lex, Lexeme};
+pub use lint::Lint;
+pub use matcher::{Backend, BudgetExceeded, Captures, CompiledRe, Matcher, Span};
+pub use multi::MultiMatcher;
+pub use pike::{Inst, Program};
+pub use spans::SpannedRe;
+use spans::SpanTree;
 
 #[derive(Debug)]
 pub enum ReErrorKind {
@@ -9,25 +41,172 @@ pub enum ReErrorKind {
 	OutOfRange,
 	InvalidChar,
 	InvalidInt,
-	InvalidRange
+	InvalidRange,
+	RepeatedAnchor
 }
 
 #[derive(Debug)]
 pub struct ReErrorInfo {
+	// byte range at..end of the pattern the error is about
 	at: usize,
+	end: usize,
+	// byte range parse_regexp_collect drops to get past the error, None for
+	// the char at at
+	repair: Option<Range<usize>>,
 	msg: String
 }
 
 pub type ReError = (ReErrorKind, ReErrorInfo);
 
+impl fmt::Display for ReErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			ReErrorKind::NonAsciiChar => "non-ASCII character",
+			ReErrorKind::BadSplit => "invalid alternation or repetition",
+			ReErrorKind::OutOfRange => "unexpected end of pattern",
+			ReErrorKind::InvalidChar => "invalid character",
+			ReErrorKind::InvalidInt => "invalid integer",
+			ReErrorKind::InvalidRange => "invalid range",
+			ReErrorKind::RepeatedAnchor => "repeated anchor"
+		})
+	}
+}
+
+// The message and its byte range, a ReError being reported as
+// "{kind}: {info}"
+impl fmt::Display for ReErrorInfo {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} at {}..{}", self.msg, self.at, self.end)
+	}
+}
+
+// Options changing how a regexp is parsed or matched
+#[derive(Debug, Clone, Copy)]
+pub struct ReFlags {
+	// ^ and $ also match right after and right before a '\n'
+	pub multiline: bool,
+	// . consumes a whole grapheme cluster instead of a single char
+	pub grapheme_clusters: bool,
+	// . also matches '\n', which [^\n] never does
+	pub dotall: bool,
+	// an empty branch of an alternation, as in (a|), matches the empty
+	// string instead of being an error
+	pub allow_empty_alternation: bool,
+	// ASCII whitespace around the pattern, like the new line ending a line
+	// read from a file, is not part of it
+	pub trim: bool,
+	// is_match backtracks on inputs shorter than this many bytes, where
+	// building an Nfa costs more than it saves, and simulates an Nfa on the
+	// longer ones and for patterns with an exponential risk
+	pub nfa_threshold: usize,
+	// the Nfa copies the body of x{a,b} b times where backtracking counts the
+	// repetitions, so is_match keeps backtracking when the Nfa would have more
+	// states than this
+	pub max_nfa_size: usize
+}
+
+impl Default for ReFlags {
+	fn default() -> Self {
+		ReFlags{multiline: false, grapheme_clusters: false, dotall: false, allow_empty_alternation: false, trim: false, nfa_threshold: 64, max_nfa_size: 10_000}
+	}
+}
+
+#[derive(Clone)]
 pub enum Re {
+	// matches only the empty string
+	Empty,
 	Char(char),
 	Or(Rc<Re>, Rc<Re>),
 	And(Rc<Re>, Rc<Re>),
 	Kleen(Rc<Re>),
 	OneOrMore(Rc<Re>),
+	// zero or one occurrence, written ?
+	Optional(Rc<Re>),
 	Repeat(Rc<Re>, usize, usize),
-	AnyChar
+	// ., any char but '\n'. Patterns only match text, so this is always a
+	// whole char and never a single byte of it.
+	AnyChar,
+	// zero-width anchors for ^ and $
+	Start,
+	End,
+	// zero-width anchors for \A and \z, always the ends of the whole input
+	TextStart,
+	TextEnd,
+	Class(CharClass),
+	// capturing group, numbered from 1 in opening parenthesis order
+	Group(Rc<Re>, usize),
+	// capturing group written (?P<name>...) or (?<name>...), numbered along
+	// with the other groups
+	NamedGroup(Rc<Re>, usize, String),
+	// non-capturing group (?>...) keeping only the first way its body
+	// matches. Only the backtracking matcher gives back nothing, the other
+	// backends match it like a plain group.
+	Atomic(Rc<Re>),
+	// n-ary forms of And and Or, never produced by the parser (see to_nary).
+	// An Alt without alternatives is the regexp matching nothing (see
+	// Re::never), which simplify propagates and every backend rejects.
+	Concat(Vec<Rc<Re>>),
+	Alt(Vec<Rc<Re>>)
+}
+
+// Set of chars given as inclusive ranges, matching the chars outside of
+// the ranges when negated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharClass {
+	pub ranges: Vec<(char, char)>,
+	pub negated: bool
+}
+
+impl CharClass {
+	pub fn new(ranges: Vec<(char, char)>, negated: bool) -> Self {
+		CharClass{ranges, negated}
+	}
+
+	// Whether c is in the set, taking the negation into account
+	pub fn contains(&self, c: char) -> bool {
+		self.ranges.iter().any(|(low, high)| *low <= c && c <= *high) != self.negated
+	}
+}
+
+// Token of the postfix (reverse polish) form of a regexp, as produced by the
+// shunting-yard step that usually precedes a Thompson construction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+	Empty,
+	Char(char),
+	AnyChar,
+	Concat,
+	Alt,
+	Star,
+	Plus,
+	Question,
+	Repeat(usize, usize),
+	Start,
+	End,
+	TextStart,
+	TextEnd,
+	Class(CharClass)
+}
+
+// Role of a piece of the pattern, for syntax highlighting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenRole {
+	Literal,
+	// ., a bracket class or a Perl class escape like \d
+	Class,
+	Anchor,
+	Quantifier,
+	GroupOpen,
+	GroupClose,
+	Alternation,
+	Comment
+}
+
+// Byte range of the pattern covered by a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSpan {
+	pub range: Range<usize>,
+	pub role: TokenRole
 }
 
 impl PartialEq for Re {
@@ -53,8 +232,38 @@ impl PartialEq for Re {
 				Re::OneOrMore(c2) => c1.eq(c2),
 				_ => false
 			},
-			Re::AnyChar => match other {
-				Re::AnyChar => true,
+			Re::Optional(c1) => match other {
+				Re::Optional(c2) => c1.eq(c2),
+				_ => false
+			},
+			Re::Empty => matches!(other, Re::Empty),
+			Re::AnyChar => matches!(other, Re::AnyChar),
+			Re::Start => matches!(other, Re::Start),
+			Re::End => matches!(other, Re::End),
+			Re::TextStart => matches!(other, Re::TextStart),
+			Re::TextEnd => matches!(other, Re::TextEnd),
+			Re::Class(c1) => match other {
+				Re::Class(c2) => c1 == c2,
+				_ => false
+			},
+			Re::Group(c1, i1) => match other {
+				Re::Group(c2, i2) => c1.eq(c2) && i1 == i2,
+				_ => false
+			},
+			Re::NamedGroup(c1, i1, n1) => match other {
+				Re::NamedGroup(c2, i2, n2) => c1.eq(c2) && i1 == i2 && n1 == n2,
+				_ => false
+			},
+			Re::Atomic(c1) => match other {
+				Re::Atomic(c2) => c1.eq(c2),
+				_ => false
+			},
+			Re::Concat(c1) => match other {
+				Re::Concat(c2) => c1 == c2,
+				_ => false
+			},
+			Re::Alt(c1) => match other {
+				Re::Alt(c2) => c1 == c2,
 				_ => false
 			},
 			Re::Repeat(c1, a1, b1) => match other {
@@ -63,35 +272,190 @@ impl PartialEq for Re {
 			},
 		}
 	}
+}
+
+impl Eq for Re {}
 
-	fn ne(&self, other: &Self) -> bool {
-		!self.eq(other)
+impl FromStr for Re {
+	type Err = ReError;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		Re::parse_regexp(string)
 	}
 }
 
-impl Eq for Re {}
+impl TryFrom<&str> for Re {
+	type Error = ReError;
+
+	fn try_from(string: &str) -> Result<Self, Self::Error> {
+		Re::parse_regexp(string)
+	}
+}
 
 impl Re {
 	pub fn parse_regexp(string: &str) -> Result<Self, ReError> {
-		let bytes = string.as_bytes();
-		match first_non_ascii(&bytes) {
-			Some(index) => return Err((
-					ReErrorKind::NonAsciiChar,
-					ReErrorInfo{at: index, msg: String::from("")}
-				)),
-			None => ()
+		Re::parse_regexp_with_flags(string, &ReFlags::default())
+	}
+
+	pub fn parse_regexp_with_flags(string: &str, flags: &ReFlags) -> Result<Self, ReError> {
+		if flags.trim {
+			// the error positions are those of the untrimmed pattern
+			let offset = string.len() - string.trim_ascii_start().len();
+			return Re::parse_regexp_with_flags(string.trim_ascii(), &ReFlags{trim: false, ..*flags})
+				.map_err(|(kind, info)| (kind, ReErrorInfo{at: info.at + offset, end: info.end + offset, ..info}));
 		}
+		parse_string(string, &mut ParseState{flags: *flags, ..ParseState::default()})
+	}
+
+	// Parse the pattern, also returning the spans of its tokens in pattern
+	// order
+	pub fn parse_with_tokens(string: &str) -> Result<(Self, Vec<TokenSpan>), ReError> {
+		let mut state = ParseState::default();
+		let reg = parse_string(string, &mut state)?;
+		Ok((reg, state.tokens))
+	}
+
+	// Parse the pattern, keeping the source span of every node
+	pub fn parse_with_spans(string: &str) -> Result<SpannedRe, ReError> {
+		let mut state = ParseState::default();
+		let re = parse_string(string, &mut state)?;
+		Ok(SpannedRe{re, spans: state.nodes.pop().unwrap()})
+	}
+
+	// Parse the pattern, reporting all its errors instead of the first one.
+	// Each error is repaired, an unterminated group being closed at the end,
+	// a reversed repeat dropped, a bad class range cut down to its first
+	// char and anything else dropping the char it is at, and the pattern
+	// parsed again. The regexp is that of the repaired pattern, None if the
+	// repairs did not make it valid.
+	pub fn parse_regexp_collect(string: &str) -> (Option<Re>, Vec<ReError>) {
+		let mut pattern: Vec<u8> = string.bytes().collect();
+		// offset in string of each byte of pattern, and of its end
+		let mut origin: Vec<usize> = (0..=string.len()).collect();
+		let mut errors = Vec::new();
 
-		match parse_or(&bytes, 0) {
-			Ok((reg, _)) => Ok(reg),
-			Err(err) => Err(err)
+		for _ in 0..=2 * string.len() {
+			let Ok(repaired) = core::str::from_utf8(&pattern) else { break };
+			let (kind, info) = match Re::parse_regexp(repaired) {
+				Ok(reg) => return (Some(reg), errors),
+				Err(error) => error
+			};
+
+			let original = |index: usize| origin[index.min(origin.len() - 1)];
+			let (at, end) = (original(info.at), original(info.end));
+
+			let unclosed = matches!(kind, ReErrorKind::OutOfRange) && info.end == pattern.len() && pattern.get(info.at) == Some(&b'(');
+			if unclosed {
+				pattern.push(b')');
+				origin.push(string.len());
+			}
+			else {
+				// a char that is not ASCII is dropped whole
+				let repair = info.repair.clone().unwrap_or_else(|| {
+					let start = info.at.min(pattern.len() - 1);
+					start..start + repaired[start..].chars().next().map_or(1, char::len_utf8)
+				});
+				pattern.drain(repair.clone());
+				origin.drain(repair);
+			}
+			errors.push((kind, ReErrorInfo{at, end, ..info}));
 		}
+		(None, errors)
 	}
 
 	pub fn match_one(&self, string: &str) -> String {
 		String::from("Nothing")
 	}
 
+	// Drop a leading ^ and a trailing $ of the top-level concatenation, so an
+	// anchored pattern can be reused for unanchored search. Anchors nested in
+	// an alternation or a group are kept, a pattern made only of anchors
+	// becomes Empty.
+	pub fn strip_anchors(&self) -> Re {
+		let body = match self {
+			Re::And(left, right) if **left == Re::Start => right.as_ref(),
+			Re::Start => return Re::Empty,
+			_ => self
+		};
+
+		if *body == Re::End {
+			return Re::Empty;
+		}
+		match body.strip_end_anchor() {
+			Some(reg) => reg,
+			None => body.clone()
+		}
+	}
+
+	fn strip_end_anchor(&self) -> Option<Re> {
+		match self {
+			Re::And(left, right) if **right == Re::End => Some(left.as_ref().clone()),
+			Re::And(left, right) => right.strip_end_anchor()
+				.map(|right| Re::And(left.clone(), Rc::from(right))),
+			_ => None
+		}
+	}
+
+	pub fn to_postfix(&self) -> Vec<Token> {
+		let mut tokens = Vec::new();
+		self.push_postfix(&mut tokens);
+		tokens
+	}
+
+	fn push_postfix(&self, tokens: &mut Vec<Token>) {
+		match self {
+			Re::Char(c) => tokens.push(Token::Char(*c)),
+			Re::Empty => tokens.push(Token::Empty),
+			Re::AnyChar => tokens.push(Token::AnyChar),
+			Re::Start => tokens.push(Token::Start),
+			Re::End => tokens.push(Token::End),
+			Re::TextStart => tokens.push(Token::TextStart),
+			Re::TextEnd => tokens.push(Token::TextEnd),
+			Re::Class(class) => tokens.push(Token::Class(class.clone())),
+			// groups only change the tree, they have no operator
+			Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.push_postfix(tokens),
+			// emitted as the equivalent chain of binary operators
+			Re::Concat(children) => Re::push_postfix_chain(children, Token::Concat, tokens),
+			Re::Alt(children) => Re::push_postfix_chain(children, Token::Alt, tokens),
+			Re::Kleen(c) => {
+				c.push_postfix(tokens);
+				tokens.push(Token::Star);
+			},
+			Re::OneOrMore(c) => {
+				c.push_postfix(tokens);
+				tokens.push(Token::Plus);
+			},
+			Re::Optional(c) => {
+				c.push_postfix(tokens);
+				tokens.push(Token::Question);
+			},
+			Re::Repeat(c, a, b) => {
+				c.push_postfix(tokens);
+				tokens.push(Token::Repeat(*a, *b));
+			},
+			Re::Or(c1, c2) => {
+				c1.push_postfix(tokens);
+				c2.push_postfix(tokens);
+				tokens.push(Token::Alt);
+			},
+			Re::And(c1, c2) => {
+				c1.push_postfix(tokens);
+				c2.push_postfix(tokens);
+				tokens.push(Token::Concat);
+			},
+		}
+	}
+
+	fn push_postfix_chain(children: &[Rc<Re>], operator: Token, tokens: &mut Vec<Token>) {
+		for (i, child) in children.iter().enumerate() {
+			child.push_postfix(tokens);
+			if i > 0 {
+				tokens.push(operator.clone());
+			}
+		}
+	}
+
+	#[cfg(feature = "std")]
 	pub fn debug_print(&self) {
 		match self {
 			Re::Char(c) => print!("{}", c),
@@ -119,9 +483,66 @@ impl Re {
 				c.debug_print();
 				print!(")+");
 			},
+			Re::Optional(c) => {
+				print!("(");
+				c.debug_print();
+				print!(")?");
+			},
+			Re::Empty => (),
 			Re::AnyChar => {
 				print!(".");
 			},
+			Re::Start => print!("^"),
+			Re::End => print!("$"),
+			Re::TextStart => print!("\\A"),
+			Re::TextEnd => print!("\\z"),
+			Re::Class(class) => {
+				print!("[");
+				if class.negated {
+					print!("^");
+				}
+				for (low, high) in &class.ranges {
+					if low == high {
+						print!("{low}");
+					}
+					else {
+						print!("{low}-{high}");
+					}
+				}
+				print!("]");
+			},
+			Re::Group(c, _) => {
+				print!("(");
+				c.debug_print();
+				print!(")");
+			},
+			Re::NamedGroup(c, _, name) => {
+				print!("(?P<{name}>");
+				c.debug_print();
+				print!(")");
+			},
+			Re::Atomic(c) => {
+				print!("(?>");
+				c.debug_print();
+				print!(")");
+			},
+			Re::Concat(children) => {
+				print!("(");
+				for child in children {
+					child.debug_print();
+				}
+				print!(")");
+			},
+			Re::Alt(children) => {
+				print!("(");
+				for (i, child) in children.iter().enumerate() {
+					if i > 0 {
+						print!("|");
+					}
+					child.debug_print();
+				}
+				print!(")");
+			},
 			Re::Repeat(c, a, b) => {
 				print!("(");
 				c.debug_print();
@@ -129,64 +550,182 @@ impl Re {
 			},
 		}
 	}
-}
 
-fn first_non_ascii(string: &[u8]) -> Option<usize> {
-	for i in 0..string.len() {
-		if !string[i].is_ascii() {
-			return Some(i)
+	// The tree with one node per line, each child indented by two spaces
+	// under its parent
+	pub fn debug_tree(&self) -> String {
+		let mut tree = String::new();
+		self.write_tree(0, &mut tree);
+		tree
+	}
+
+	fn write_tree(&self, depth: usize, tree: &mut String) {
+		for _ in 0..depth {
+			tree.push_str("  ");
+		}
+		let label = match self {
+			Re::Empty => String::from("Empty"),
+			Re::Char(c) => format!("Char {c:?}"),
+			Re::Or(_, _) => String::from("Or"),
+			Re::And(_, _) => String::from("And"),
+			Re::Kleen(_) => String::from("Kleen"),
+			Re::OneOrMore(_) => String::from("OneOrMore"),
+			Re::Optional(_) => String::from("Optional"),
+			Re::Repeat(_, a, b) => format!("Repeat {{{a},{b}}}"),
+			Re::AnyChar => String::from("AnyChar"),
+			Re::Start => String::from("Start"),
+			Re::End => String::from("End"),
+			Re::TextStart => String::from("TextStart"),
+			Re::TextEnd => String::from("TextEnd"),
+			Re::Class(class) => {
+				let mut label = String::from(if class.negated { "Class [^" } else { "Class [" });
+				for (low, high) in &class.ranges {
+					label.extend(low.escape_debug());
+					if low != high {
+						label.push('-');
+						label.extend(high.escape_debug());
+					}
+				}
+				label.push(']');
+				label
+			},
+			Re::Group(_, index) => format!("Group {index}"),
+			Re::NamedGroup(_, index, name) => format!("Group {index} {name:?}"),
+			Re::Atomic(_) => String::from("Atomic"),
+			Re::Concat(_) => String::from("Concat"),
+			Re::Alt(_) => String::from("Alt")
+		};
+		tree.push_str(&label);
+		tree.push('\n');
+		for child in self.children() {
+			child.write_tree(depth + 1, tree);
 		}
 	}
-	None
 }
 
-fn parse_or(string: &[u8], index: usize) -> Result<(Re, usize), ReError> {
-	let (left, new_index) = parse_and(string, index)?;
+// Mutable state shared by the parsing functions
+#[derive(Default)]
+struct ParseState {
+	group_count: usize,
+	flags: ReFlags,
+	// tokens met so far, in pattern order
+	tokens: Vec<TokenSpan>,
+	// source spans of the nodes parsed so far whose parent is not built yet
+	nodes: Vec<SpanTree>,
+	// inside \Q...\E, where every char is a literal
+	quoted: bool
+}
 
-	if new_index < string.len() {
-		let sep = string[new_index] as char;
-		if sep == '|' {
-			let (right, end_index) = parse_or(string, new_index+1)?;
-			Ok((Re::Or(Rc::from(left),Rc::from(right)), end_index))
-		}
-		else if sep == ')' {
-			Ok((left, new_index+1))
+impl ParseState {
+	fn push_token(&mut self, range: Range<usize>, role: TokenRole) {
+		self.tokens.push(TokenSpan{range, role});
+	}
+
+	// Record the span of a node built from the last children nodes
+	fn push_node(&mut self, range: Range<usize>, children: usize) {
+		let children = self.nodes.split_off(self.nodes.len() - children);
+		self.nodes.push(SpanTree{span: Span{start: range.start, end: range.end}, children});
+	}
+}
+
+fn parse_string(string: &str, state: &mut ParseState) -> Result<Re, ReError> {
+	let bytes = string.as_bytes();
+	if let Some(index) = first_non_ascii(bytes) {
+		return Err((
+			ReErrorKind::NonAsciiChar,
+			ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("")}
+		));
+	}
+
+	// the empty pattern matches the empty string
+	if bytes.is_empty() {
+		state.push_node(0..0, 0);
+		return Ok(Re::Empty);
+	}
+
+	let (reg, end) = parse_or(bytes, 0, state)?;
+
+	// parse_or only stops early on a ')' that closes no group
+	if end < bytes.len() {
+		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: end, end: end+1, repair: None, msg: String::from("Unmatched ')'")}));
+	}
+
+	Ok(reg)
+}
+
+fn first_non_ascii(string: &[u8]) -> Option<usize> {
+	string.iter().position(|c| !c.is_ascii())
+}
+
+fn parse_or(string: &[u8], index: usize, state: &mut ParseState) -> Result<(Re, usize), ReError> {
+	if !state.flags.allow_empty_alternation && index < string.len() && string[index] == b'|' {
+		return Err((ReErrorKind::BadSplit, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("empty alternation branch before '|'")}));
+	}
+
+	let (left, new_index) = if state.flags.allow_empty_alternation && index < string.len() && string[index] == b'|' {
+		state.push_node(index..index, 0);
+		(Re::Empty, index)
+	}
+	else {
+		parse_and(string, index, state)?
+	};
+
+	// The closing parenthesis of a group is left for parse_atom to consume
+	if new_index < string.len() && string[new_index] == b'|' {
+		state.push_token(new_index..new_index+1, TokenRole::Alternation);
+		let after_bar = new_index+1;
+		let (right, end_index) = if state.flags.allow_empty_alternation && (after_bar >= string.len() || string[after_bar] == b')') {
+			state.push_node(after_bar..after_bar, 0);
+			(Re::Empty, after_bar)
 		}
 		else {
-			Ok((left, new_index))
-			// Err((
-			// 	ReErrorKind::BadSplit,
-			// 	ReErrorInfo{at: new_index, msg: String::from("Expected '('")}
-			// ))
-		}
+			parse_or(string, after_bar, state)?
+		};
+		state.push_node(index..end_index, 2);
+		Ok((Re::Or(Rc::from(left),Rc::from(right)), end_index))
 	}
 	else {
 		Ok((left, new_index))
 	}
 }
 
-fn parse_and(string: &[u8], index: usize) -> Result<(Re, usize), ReError> {
-	let (left, after_left_id) = parse_postfix(string, index)?;
+fn parse_and(string: &[u8], index: usize, state: &mut ParseState) -> Result<(Re, usize), ReError> {
+	let index = skip_comments(string, index, state)?;
+	let (left, after_left_id) = parse_postfix(string, index, state)?;
+	let after_left_id = skip_comments(string, after_left_id, state)?;
 
 	// End of string, do not match more
 	if after_left_id >= string.len() {
 		return Ok((left, after_left_id));
 	}
 	
-	// We have reached a closing parenthesis so we return the reg
-	if string[after_left_id] == ')' as u8 {
-		return Ok((left, after_left_id+1));
-	}
-	if string[after_left_id] == '|' as u8 {
+	// A closing parenthesis or an alternation ends the concatenation
+	if !state.quoted && (string[after_left_id] == b')' || string[after_left_id] == b'|') {
 		return Ok((left, after_left_id));
 	}
 
-	// Find the right reg
-	let (right, after_right_id) = parse_or(string, after_left_id)?;
+	// Find the right reg, which must not extend past the next '|' so that
+	// ab|c is parsed as (ab)|c
+	let (right, after_right_id) = parse_and(string, after_left_id, state)?;
 
+	state.push_node(index..after_right_id, 2);
 	Ok((Re::And(Rc::from(left), Rc::from(right)), after_right_id))
 }
 
+// Skip the (?#...) comments starting at index, returning the index right
+// after them
+fn skip_comments(string: &[u8], index: usize, state: &mut ParseState) -> Result<usize, ReError> {
+	let mut index = index;
+	while !state.quoted && string[index..].starts_with(b"(?#") {
+		let Some(length) = string[index..].iter().position(|c| *c == b')') else {
+			return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: index+3, repair: None, msg: String::from("Unterminated comment")}));
+		};
+		state.push_token(index..index+length+1, TokenRole::Comment);
+		index += length+1;
+	}
+	Ok(index)
+}
+
 fn parse_number(string: &[u8], index: usize) -> Option<(usize, usize)> {
 	let mut current_index = index;
 	let mut current_string = String::new();
@@ -202,87 +741,438 @@ fn parse_number(string: &[u8], index: usize) -> Option<(usize, usize)> {
 	}
 }
 
-fn parse_postfix(string: &[u8], index: usize) -> Result<(Re, usize), ReError> {
+fn parse_postfix(string: &[u8], index: usize, state: &mut ParseState) -> Result<(Re, usize), ReError> {
 	// match an underlying atom (either a single char or a sub regexp in parenthesis)
-	let (reg, after_atom_id) = parse_atom(string, index)?;
+	let (reg, mut after_atom_id) = parse_atom(string, index, state)?;
 
-	// Look for optionnal +, *, {a,b}
+	// a quantifier right after \E applies to the last quoted char, and none
+	// can follow a char inside the quote
+	if state.quoted {
+		if !string[after_atom_id..].starts_with(b"\\E") {
+			return Ok((reg, after_atom_id));
+		}
+		state.quoted = false;
+		after_atom_id += 2;
+	}
+
+	// Look for optionnal +, *, ?, {a,b}
 	if after_atom_id >= string.len() {
 		return Ok((reg, after_atom_id));
 	}
 
-	if string[after_atom_id] == '*' as u8 {
+	// Repeating something that only asserts a position is meaningless
+	if b"*+?{".contains(&string[after_atom_id]) && reg.contains_anchor() && reg.length_bounds().1 == Some(0) {
+		return Err((ReErrorKind::RepeatedAnchor, ReErrorInfo{at: after_atom_id, end: after_atom_id+1, repair: None, msg: String::from("Cannot repeat a zero-width anchor")}));
+	}
+
+	if b"*+?".contains(&string[after_atom_id]) {
+		state.push_token(after_atom_id..after_atom_id+1, TokenRole::Quantifier);
+	}
+
+	if string[after_atom_id] == b'*' {
+		state.push_node(index..after_atom_id+1, 1);
 		Ok((Re::Kleen(Rc::from(reg)), after_atom_id+1))
 	}
-	else if string[after_atom_id] == '+' as u8 {
+	else if string[after_atom_id] == b'+' {
+		state.push_node(index..after_atom_id+1, 1);
 		Ok((Re::OneOrMore(Rc::from(reg)), after_atom_id+1))
 	}
-	else if string[after_atom_id] == '{' as u8 {
-		// try to parse the rest 
-		match parse_number(string, after_atom_id+1) {
-			None => Err((ReErrorKind::InvalidInt, ReErrorInfo{at: after_atom_id+1, msg: String::from("Expected a positive integer")})),
-			Some((left, after_left_id)) => {
-				if after_left_id >= string.len() || string[after_left_id] != ',' as u8 {
-					return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: after_atom_id, msg: String::from("Expected a ',' for postfix [reg]{a,b}")}));
+	else if string[after_atom_id] == b'?' {
+		state.push_node(index..after_atom_id+1, 1);
+		Ok((Re::Optional(Rc::from(reg)), after_atom_id+1))
+	}
+	else if string[after_atom_id] == b'{' {
+		let (left, right, end) = parse_repeat(string, after_atom_id)?;
+		state.push_token(after_atom_id..end, TokenRole::Quantifier);
+		state.push_node(index..end, 1);
+		// without an upper bound, x{0,} is x* and x{1,} is x+
+		match right {
+			Some(right) => Ok((Re::Repeat(Rc::from(reg), left, right), end)),
+			None if left == 0 => Ok((Re::Kleen(Rc::from(reg)), end)),
+			None => Ok((Re::OneOrMore(Rc::from(reg)), end))
+		}
+	}
+	else {
+		Ok((reg, after_atom_id))
+	}
+}
+
+// Bounds of the {a,b} starting with the '{' at index, and the index after it.
+// The upper bound of {0,} and {1,} is None, a repeat with a higher lower
+// bound needing an upper one.
+fn parse_repeat(string: &[u8], index: usize) -> Result<(usize, Option<usize>, usize), ReError> {
+	if !string[index..].contains(&b'}') {
+		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: string.len(), repair: None, msg: String::from("Unterminated repetition, expected '}'")}));
+	}
+	match parse_number(string, index+1) {
+		None => Err((ReErrorKind::InvalidInt, ReErrorInfo{at: index+1, end: index+1, repair: None, msg: String::from("Expected a positive integer")})),
+		Some((left, after_left_id)) => {
+			if after_left_id >= string.len() || string[after_left_id] != b',' {
+				return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: after_left_id, repair: None, msg: String::from("Expected a ',' for postfix [reg]{a,b}")}));
+			}
+			if string.get(after_left_id+1) == Some(&b'}') {
+				if left > 1 {
+					return Err((ReErrorKind::InvalidInt, ReErrorInfo{at: after_left_id+1, end: after_left_id+2, repair: Some(index..after_left_id+2), msg: String::from("Expected an upper bound, only {0,} and {1,} can leave it out")}));
 				}
-				match parse_number(string, after_left_id+1) {
-					None => Err((ReErrorKind::InvalidInt, ReErrorInfo{at: after_left_id, msg: String::from("Expected a positive integer")})),
-					Some((right, after_right_id)) => {
-						if left > right {
-							return Err((ReErrorKind::InvalidRange, ReErrorInfo{at: after_atom_id, msg: String::from("left number should be lower than or equal to the right one")}));
-						}
-						
-						if after_right_id >= string.len() || string[after_right_id] != '}' as u8 {
-							return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: after_left_id, msg: String::from("Expected a '}'")}));
-						}
-
-						Ok((Re::Repeat(Rc::from(reg), left, right), after_right_id+1))
+				return Ok((left, None, after_left_id+2));
+			}
+			match parse_number(string, after_left_id+1) {
+				None => Err((ReErrorKind::InvalidInt, ReErrorInfo{at: after_left_id, end: after_left_id+1, repair: None, msg: String::from("Expected a positive integer")})),
+				Some((right, after_right_id)) => {
+					if left > right {
+						return Err((ReErrorKind::InvalidRange, ReErrorInfo{at: index, end: after_right_id, repair: Some(index..after_right_id+1), msg: String::from("left number should be lower than or equal to the right one")}));
+					}
+					
+					if after_right_id >= string.len() || string[after_right_id] != b'}' {
+						return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: after_left_id, end: after_right_id, repair: None, msg: String::from("Expected a '}'")}));
 					}
+					Ok((left, Some(right), after_right_id+1))
 				}
 			}
 		}
 	}
-	else {
-		Ok((reg, after_atom_id))
+}
+
+// Chars with a special meaning, they must be escaped to be matched literally
+const META_CHARS: &[u8] = b".^$|()*+?{}[]\\";
+
+// Pattern matching text literally, with a backslash before each meta char
+// and the non-ASCII chars written as \u{H...}
+pub fn escape(text: &str) -> String {
+	let mut pattern = String::with_capacity(text.len());
+	for c in text.chars() {
+		if !c.is_ascii() {
+			pattern.push_str(&format!("\\u{{{:X}}}", c as u32));
+			continue;
+		}
+		if META_CHARS.contains(&(c as u8)) {
+			pattern.push('\\');
+		}
+		pattern.push(c);
+	}
+	pattern
+}
+
+// Class written alone, like the allowed chars of a config field. The whole
+// input must be a single [...] class.
+pub fn parse_class(input: &str) -> Result<Re, ReError> {
+	let string = input.as_bytes();
+	if let Some(index) = first_non_ascii(string) {
+		return Err((ReErrorKind::NonAsciiChar, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("")}));
 	}
+	if string.first() != Some(&b'[') {
+		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: 0, end: string.len().min(1), repair: None, msg: String::from("Expected a '['")}));
+	}
+
+	let (class, end) = parse_bracket_class(string, 0)?;
+	if end < string.len() {
+		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: end, end: string.len(), repair: None, msg: String::from("Expected the end of the class")}));
+	}
+	Ok(Re::Class(class))
 }
 
-fn parse_atom(string: &[u8], index: usize) -> Result<(Re,usize), ReError> {
+fn parse_atom(string: &[u8], index: usize, state: &mut ParseState) -> Result<(Re,usize), ReError> {
 	// If we try to find an atom out of range, there must be an issue
 	if index >= string.len() {
-		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, msg: String::from("Expected an atom")}));
+		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: index, repair: None, msg: String::from("Expected an atom")}));
 	}
 
-	// for now, the parser only supports chars in the range [a-zA-Z0-9], we should use a separate function to take into account all other chars as well as \\, \(, ...
-	if (string[index] >= 'a' as u8 && string[index] <= 'z' as u8)
-		|| (string[index] >= 'A' as u8 && string[index] <= 'Z' as u8)
-		|| (string[index] >= '0' as u8 && string[index] <= '9' as u8) {
+	// every char without a special meaning matches itself, as does any char
+	// quoted by \Q...\E
+	if state.quoted || !META_CHARS.contains(&string[index]) {
+		state.push_token(index..index+1, TokenRole::Literal);
+		state.push_node(index..index+1, 0);
 		Ok((Re::Char(string[index] as char), index+1))
 	}
 
 	// match any char
-	else if string[index] == '.' as u8 {
+	else if string[index] == b'.' {
+		state.push_token(index..index+1, TokenRole::Class);
+		state.push_node(index..index+1, 0);
 		Ok((Re::AnyChar, index+1))
 	}
 
+	// match the start or the end of the input
+	else if string[index] == b'^' {
+		state.push_token(index..index+1, TokenRole::Anchor);
+		state.push_node(index..index+1, 0);
+		Ok((Re::Start, index+1))
+	}
+	else if string[index] == b'$' {
+		state.push_token(index..index+1, TokenRole::Anchor);
+		state.push_node(index..index+1, 0);
+		Ok((Re::End, index+1))
+	}
+
+	// start a quote, the rest of the pattern being quoted without a \E. The
+	// first quoted char is the atom, an empty quote being the empty string.
+	else if string[index..].starts_with(b"\\Q") {
+		let body = index+2;
+		if body >= string.len() || string[body..].starts_with(b"\\E") {
+			let end = (body+2).min(string.len());
+			state.push_token(index..end, TokenRole::Literal);
+			state.push_node(index..end, 0);
+			return Ok((Re::Empty, end));
+		}
+		state.quoted = true;
+		state.push_token(index..body+1, TokenRole::Literal);
+		state.push_node(index..body+1, 0);
+		Ok((Re::Char(string[body] as char), body+1))
+	}
+
+	// match an escape sequence
+	else if string[index] == b'\\' {
+		let (reg, end) = parse_escape(string, index)?;
+		let role = match reg {
+			Re::Char(_) => TokenRole::Literal,
+			Re::Class(_) => TokenRole::Class,
+			_ => TokenRole::Anchor
+		};
+		state.push_token(index..end, role);
+		state.push_node(index..end, 0);
+		Ok((reg, end))
+	}
+
+	// match a class of chars
+	else if string[index] == b'[' {
+		let (class, end) = parse_bracket_class(string, index)?;
+		state.push_token(index..end, TokenRole::Class);
+		state.push_node(index..end, 0);
+		Ok((Re::Class(class), end))
+	}
+
+	// match an atomic group, which does not capture
+	else if string[index..].starts_with(b"(?>") {
+		let (reg, end) = parse_group_body(string, index, index+3, state)?;
+		state.push_node(index..end, 1);
+		Ok((Re::Atomic(Rc::from(reg)), end))
+	}
+
+	// a non-capturing group is only its body, whose span then covers the
+	// parentheses
+	else if string[index..].starts_with(b"(?:") {
+		let (reg, end) = parse_group_body(string, index, index+3, state)?;
+		if let Some(node) = state.nodes.last_mut() {
+			node.span = Span{start: index, end};
+		}
+		Ok((reg, end))
+	}
+
 	// match a subexpression
-	else if string[index] == '(' as u8 {
-		return parse_or(string, index+1);
+	else if string[index] == b'(' {
+		state.group_count += 1;
+		let group = state.group_count;
+		let (name, body_start) = parse_group_name(string, index)?;
+		let (reg, end) = parse_group_body(string, index, body_start, state)?;
+		state.push_node(index..end, 1);
+		match name {
+			Some(name) => Ok((Re::NamedGroup(Rc::from(reg), group, name), end)),
+			None => Ok((Re::Group(Rc::from(reg), group), end))
+		}
+	}
+
+	// a quantifier where an atom is expected has nothing to apply to
+	else if b"*+?{".contains(&string[index]) {
+		Err((ReErrorKind::BadSplit, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("nothing to repeat")}))
 	}
 
-	// Unsupported char
+	// Meta char that cannot start an atom
 	else {
-		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, msg: String::from("Expected a char in [a-zA-Z0-9]")}));
+		Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("Expected an atom")}))
+	}
+}
+
+// Parse the body of the group opened at index up to its ')', returning the
+// index after it
+fn parse_group_body(string: &[u8], index: usize, body_start: usize, state: &mut ParseState) -> Result<(Re, usize), ReError> {
+	state.push_token(index..body_start, TokenRole::GroupOpen);
+	let (reg, end) = parse_or(string, body_start, state)?;
+	if end >= string.len() || string[end] != b')' {
+		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end, repair: None, msg: String::from("Expected a ')'")}));
+	}
+	state.push_token(end..end+1, TokenRole::GroupClose);
+	Ok((reg, end+1))
+}
+
+// Name of the group opened at index when it starts with (?P<name> or
+// (?<name>, and the index its body starts at
+fn parse_group_name(string: &[u8], index: usize) -> Result<(Option<String>, usize), ReError> {
+	let name_start = if string[index+1..].starts_with(b"?P<") {
+		index+4
+	}
+	else if string[index+1..].starts_with(b"?<") {
+		index+3
+	}
+	else {
+		return Ok((None, index+1));
+	};
+
+	let name_end = name_start + string[name_start..].iter()
+		.take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+		.count();
+	if name_end >= string.len() {
+		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: name_end, repair: None, msg: String::from("Expected a '>' to end the group name")}));
+	}
+	if string[name_end] != b'>' {
+		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: name_end, end: name_end+1, repair: None, msg: String::from("Invalid char in a group name")}));
+	}
+	if name_end == name_start || string[name_start].is_ascii_digit() {
+		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: name_start, end: name_end, repair: None, msg: String::from("A group name must start with a letter or '_'")}));
+	}
+
+	let name = core::str::from_utf8(&string[name_start..name_end]).unwrap();
+	Ok((Some(String::from(name)), name_end+1))
+}
+
+fn parse_escape(string: &[u8], index: usize) -> Result<(Re, usize), ReError> {
+	if index+1 >= string.len() {
+		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("Expected a char after '\\'")}));
+	}
+
+	match string[index+1] {
+		b'A' => Ok((Re::TextStart, index+2)),
+		b'z' => Ok((Re::TextEnd, index+2)),
+		b'd' | b'D' | b'w' | b'W' | b's' | b'S' => Ok((Re::Class(class_escape(string[index+1])), index+2)),
+		c if META_CHARS.contains(&c) => Ok((Re::Char(c as char), index+2)),
+		_ => match parse_char_escape(string, index)? {
+			Some((c, end)) => Ok((Re::Char(c), end)),
+			None => Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, end: index+2, repair: None, msg: String::from("Unsupported escape sequence")}))
+		}
+	}
+}
+
+// Escape of a single char at index, a control char like \t, \xHH or
+// \u{H...}, None if it is another escape
+fn parse_char_escape(string: &[u8], index: usize) -> Result<Option<(char, usize)>, ReError> {
+	let c = match string[index+1] {
+		b't' => '\t',
+		b'n' => '\n',
+		b'r' => '\r',
+		b'f' => '\x0C',
+		b'v' => '\x0B',
+		b'0' => '\0',
+		b'x' => {
+			let digits = string.get(index+2..index+4).filter(|digits| digits.iter().all(u8::is_ascii_hexdigit));
+			let Some(digits) = digits else {
+				return Err((ReErrorKind::InvalidInt, ReErrorInfo{at: index, end: (index+4).min(string.len()), repair: None, msg: String::from("Expected two hex digits after '\\x'")}));
+			};
+			return Ok(Some((parse_hex(digits) as u8 as char, index+4)));
+		},
+		b'u' => {
+			if string.get(index+2) != Some(&b'{') {
+				return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, end: index+2, repair: None, msg: String::from("Expected a '{' after '\\u'")}));
+			}
+			let digits_end = index+3 + string[index+3..].iter().take_while(|c| c.is_ascii_hexdigit()).count();
+			if digits_end >= string.len() || string[digits_end] != b'}' {
+				return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: digits_end, repair: None, msg: String::from("Expected hex digits and a '}' after '\\u{'")}));
+			}
+			let digits = &string[index+3..digits_end];
+			let c = (1..=6).contains(&digits.len()).then(|| char::from_u32(parse_hex(digits))).flatten();
+			let Some(c) = c else {
+				return Err((ReErrorKind::InvalidInt, ReErrorInfo{at: index, end: digits_end+1, repair: None, msg: String::from("Invalid unicode scalar value")}));
+			};
+			return Ok(Some((c, digits_end+1)));
+		},
+		_ => return Ok(None)
+	};
+	Ok(Some((c, index+2)))
+}
+
+// Value of at most 8 hex digits
+fn parse_hex(digits: &[u8]) -> u32 {
+	digits.iter().fold(0, |value, digit| value * 16 + (*digit as char).to_digit(16).unwrap())
+}
+
+// Parse the class starting with the '[' at index
+fn parse_bracket_class(string: &[u8], index: usize) -> Result<(CharClass, usize), ReError> {
+	let mut current_index = index+1;
+	let negated = current_index < string.len() && string[current_index] == b'^';
+	if negated {
+		current_index += 1;
 	}
+
+	let mut ranges = Vec::new();
+	loop {
+		if current_index >= string.len() {
+			return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: current_index, repair: None, msg: String::from("Expected a ']'")}));
+		}
+		if string[current_index] == b']' {
+			break;
+		}
+
+		let item_index = current_index;
+		let (low, after_low_id) = parse_class_item(string, current_index, &mut ranges)?;
+		current_index = after_low_id;
+
+		let Some(low) = low else {
+			continue;
+		};
+
+		// a '-' is a literal when it cannot be a range, as at the end of the class
+		if current_index+1 < string.len() && string[current_index] == b'-' && string[current_index+1] != b']' {
+			let (high, after_high_id) = parse_class_item(string, current_index+1, &mut ranges)?;
+			match high {
+				None => return Err((ReErrorKind::InvalidRange, ReErrorInfo{at: item_index, end: after_high_id, repair: Some(after_low_id..after_high_id), msg: String::from("Expected a char to end the range")})),
+				Some(high) if low > high => return Err((ReErrorKind::InvalidRange, ReErrorInfo{at: item_index, end: after_high_id, repair: Some(after_low_id..after_high_id), msg: String::from("Range start should be lower than or equal to its end")})),
+				Some(high) => ranges.push((low, high))
+			}
+			current_index = after_high_id;
+		}
+		else {
+			ranges.push((low, low));
+		}
+	}
+
+	if ranges.is_empty() {
+		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, end: current_index+1, repair: None, msg: String::from("Expected a char in the class")}));
+	}
+	Ok((CharClass::new(ranges, negated), current_index+1))
+}
+
+// Parse a single char of a class, or a Perl class whose ranges are directly
+// added to ranges, in which case no char is returned
+fn parse_class_item(string: &[u8], index: usize, ranges: &mut Vec<(char, char)>) -> Result<(Option<char>, usize), ReError> {
+	if string[index] != b'\\' {
+		return Ok((Some(string[index] as char), index+1));
+	}
+	if index+1 >= string.len() {
+		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("Expected a char after '\\'")}));
+	}
+
+	match string[index+1] {
+		b'd' | b'w' | b's' => {
+			ranges.extend(class_escape(string[index+1]).ranges);
+			Ok((None, index+2))
+		},
+		c if c.is_ascii_punctuation() => Ok((Some(c as char), index+2)),
+		_ => match parse_char_escape(string, index)? {
+			Some((c, end)) => Ok((Some(c), end)),
+			None => Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, end: index+2, repair: None, msg: String::from("Unsupported escape sequence in a class")}))
+		}
+	}
+}
+
+// Perl classes \d, \w, \s, an uppercase letter negates the class
+fn class_escape(letter: u8) -> CharClass {
+	let ranges = match letter.to_ascii_lowercase() {
+		b'd' => vec![('0', '9')],
+		b'w' => vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+		_ => vec![('\t', '\r'), (' ', ' ')]
+	};
+	CharClass::new(ranges, letter.is_ascii_uppercase())
 }
 
 #[cfg(test)]
 mod tests {
-	use std::rc::Rc;
+	use alloc::format;
+	use alloc::rc::Rc;
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use core::ops::Range;
 
-	use crate::regexp::{parse_atom, Re, ReErrorKind};
+	use crate::regexp::{escape, parse_atom, parse_class, CharClass, Re, ReError, ReErrorKind, ReFlags, Token, TokenRole};
 
-	use super::{first_non_ascii, parse_number, parse_postfix};
+	use super::{first_non_ascii, parse_number, parse_postfix, ParseState};
 
 	#[test]
 	fn parse_integer() {
@@ -304,26 +1194,23 @@ mod tests {
 	fn atom_parsing() {
 		let string = "a._?".as_bytes();
 
-		match parse_atom(string, 0) {
+		match parse_atom(string, 0, &mut ParseState::default()) {
 			Ok((reg, index)) => {
 				assert_eq!(index, 1);
 				assert!(reg == Re::Char('a'));
 			},
-			Err(_) => assert!(false)
+			Err(_) => panic!()
 		}
 
-		match parse_atom(string, 1) {
+		match parse_atom(string, 1, &mut ParseState::default()) {
 			Ok((reg, index)) => {
 				assert_eq!(index, 2);
 				assert!(reg == Re::AnyChar)
 			},
-			Err(_) => assert!(false)
+			Err(_) => panic!()
 		}
 
-		match parse_atom(string, 3) {
-			Ok(_) => assert!(false),
-			Err(_) => ()
-		}
+		assert!(parse_atom(string, 3, &mut ParseState::default()).is_err());
 	}
 
 	#[test]
@@ -339,8 +1226,8 @@ mod tests {
 	fn postfix_parsing() {
 		let string = "a{22,144}a{144,22}aa+*b".as_bytes();
 
-		match parse_postfix(string, 0) {
-			Err(_) => assert!(false),
+		match parse_postfix(string, 0, &mut ParseState::default()) {
+			Err(_) => panic!(),
 			Ok((reg, index)) => {
 				assert_eq!(index, 9);
 				let r = Re::Repeat(Rc::from(Re::Char('a')), 22, 144);
@@ -348,16 +1235,482 @@ mod tests {
 			}
 		}
 
-		match parse_postfix(string, 9) {
+		match parse_postfix(string, 9, &mut ParseState::default()) {
 			Err((err_kind, _)) => {
 				assert!(matches!(err_kind, ReErrorKind::InvalidRange));
 			},
-			Ok(_) => assert!(false)
+			Ok(_) => panic!()
 		}
 	}
 
+	#[test]
+	fn optional_parsing() {
+		let reg = Re::parse_regexp("ab?").unwrap();
+		assert!(reg == Re::And(Rc::from(Re::Char('a')), Rc::from(Re::Optional(Rc::from(Re::Char('b'))))));
+		assert!(reg.is_match("a"));
+		assert!(reg.is_match("ab"));
+		assert!(!reg.is_match("abb"));
+		assert_eq!(reg.to_postfix(), vec![Token::Char('a'), Token::Char('b'), Token::Question, Token::Concat]);
+
+		assert!(matches!(Re::parse_regexp("^?"), Err((ReErrorKind::RepeatedAnchor, _))));
+		assert!(matches!(Re::parse_regexp("?a"), Err((ReErrorKind::BadSplit, _))));
+	}
+
+	#[test]
+	fn postfix_tokens() {
+		// ab|c
+		let reg = Re::Or(
+			Rc::from(Re::And(Rc::from(Re::Char('a')), Rc::from(Re::Char('b')))),
+			Rc::from(Re::Char('c'))
+		);
+
+		assert_eq!(reg.to_postfix(), vec![
+			Token::Char('a'), Token::Char('b'), Token::Concat, Token::Char('c'), Token::Alt
+		]);
+	}
+
+	fn and(left: Re, right: Re) -> Re {
+		Re::And(Rc::from(left), Rc::from(right))
+	}
+
+	fn group(reg: Re, index: usize) -> Re {
+		Re::Group(Rc::from(reg), index)
+	}
+
 	#[test]
 	fn and_parsing() {
-		
+		let a = Re::Char('a');
+		let b = Re::Char('b');
+		let ab = and(a.clone(), b.clone());
+
+		assert!(Re::parse_regexp("ab").unwrap() == ab);
+		assert!(Re::parse_regexp("(a)(b)").unwrap() == and(group(a.clone(), 1), group(b.clone(), 2)));
+		assert!(Re::parse_regexp("a(b)").unwrap() == and(a.clone(), group(b.clone(), 1)));
+		assert!(Re::parse_regexp("(a)b").unwrap() == and(group(a.clone(), 1), b.clone()));
+		assert!(Re::parse_regexp("((a))((b))").unwrap() == and(group(group(a.clone(), 2), 1), group(group(b.clone(), 4), 3)));
+
+		// concatenation is right-folded, groups keep their own nesting
+		let class = |c| Re::Class(CharClass::new(vec![(c, c)], false));
+		assert!(Re::parse_regexp("[a][b]").unwrap() == and(class('a'), class('b')));
+
+		let abc = and(a.clone(), and(b.clone(), Re::Char('c')));
+		assert!(Re::parse_regexp("abc").unwrap() == abc);
+		assert!(Re::parse_regexp("(ab)c").unwrap() == and(group(ab, 1), Re::Char('c')));
+	}
+
+	#[test]
+	fn literal_and_class_atoms() {
+		assert!(Re::parse_regexp("a=;_ ").unwrap().to_postfix() == vec![
+			Token::Char('a'), Token::Char('='), Token::Char(';'), Token::Char('_'), Token::Char(' '),
+			Token::Concat, Token::Concat, Token::Concat, Token::Concat
+		]);
+
+		let digit = CharClass::new(vec![('0', '9')], false);
+		assert!(Re::parse_regexp("\\d").unwrap() == Re::Class(digit));
+		assert!(Re::parse_regexp("\\W").unwrap() == Re::Class(CharClass::new(vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')], true)));
+		assert!(matches!(Re::parse_regexp("a\\q"), Err((ReErrorKind::InvalidChar, _))));
+	}
+
+	#[test]
+	fn class_membership() {
+		let Re::Class(class) = Re::parse_regexp("[^a-z]").unwrap() else { panic!("expected a class") };
+		assert!(class.contains('5'));
+		assert!(!class.contains('b'));
+		assert!(class.contains('é'));
+
+		let class = CharClass::new(vec![('0', '9'), ('_', '_')], false);
+		assert!(class.contains('_') && class.contains('0') && !class.contains('a'));
+		assert!(!CharClass::new(Vec::new(), false).contains('a'));
+	}
+
+	#[test]
+	fn debug_tree() {
+		assert_eq!(Re::parse_regexp("(a|b)*a").unwrap().debug_tree(), concat!(
+			"And\n",
+			"  Kleen\n",
+			"    Group 1\n",
+			"      Or\n",
+			"        Char 'a'\n",
+			"        Char 'b'\n",
+			"  Char 'a'\n"
+		));
+		assert_eq!(Re::parse_regexp("(?P<x>[^a-c]){1,2}").unwrap().debug_tree(),
+			"Repeat {1,2}\n  Group 1 \"x\"\n    Class [^a-c]\n");
+		assert_eq!(Re::parse_regexp("\\s").unwrap().debug_tree(), "Class [\\t-\\r ]\n");
+	}
+
+	#[test]
+	fn char_escapes() {
+		assert!(Re::parse_regexp("a\\tb").unwrap().is_match("a\tb"));
+		assert!(Re::parse_regexp("\\x41").unwrap().is_match("A"));
+		assert!(Re::parse_regexp("\\n\\r\\f\\v\\0").unwrap().is_match("\n\r\x0C\x0B\0"));
+		assert!(Re::parse_regexp("\\u{e9}\\u{1F600}").unwrap().is_match("é😀"));
+		assert!(Re::parse_regexp("[\\t\\x30-\\x39]+").unwrap().is_match("\t42"));
+
+		match Re::parse_regexp("a\\x4g") {
+			Err((ReErrorKind::InvalidInt, info)) => assert_eq!((info.at, info.end), (1, 5)),
+			_ => panic!("\\x needs two hex digits")
+		}
+		assert!(matches!(Re::parse_regexp("\\x4"), Err((ReErrorKind::InvalidInt, _))));
+		assert!(matches!(Re::parse_regexp("\\u41"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(Re::parse_regexp("\\u{41"), Err((ReErrorKind::OutOfRange, _))));
+		assert!(matches!(Re::parse_regexp("\\u{}"), Err((ReErrorKind::InvalidInt, _))));
+		match Re::parse_regexp("x\\u{D800}") {
+			Err((ReErrorKind::InvalidInt, info)) => assert_eq!((info.at, info.end), (1, 9)),
+			_ => panic!("a surrogate is not a char")
+		}
+		assert!(matches!(Re::parse_regexp("\\u{110000}"), Err((ReErrorKind::InvalidInt, _))));
+	}
+
+	#[test]
+	fn escaped_meta_chars() {
+		assert!(Re::parse_regexp("\\.\\\\").unwrap() == Re::And(Rc::from(Re::Char('.')), Rc::from(Re::Char('\\'))));
+		assert_eq!(escape("a.b*"), "a\\.b\\*");
+		assert_eq!(escape("x=1"), "x=1");
+
+		let reg = Re::parse_regexp(&escape("a.b*")).unwrap();
+		assert!(reg.is_match("a.b*"));
+		assert!(!reg.is_match("axbbb"));
+
+		let text = "^(a|b)[c]{1,2}?+$\\";
+		assert!(Re::parse_regexp(&escape(text)).unwrap().is_match(text));
+
+		assert_eq!(escape("é.😀"), "\\u{E9}\\.\\u{1F600}");
+		let text = "café (ü)";
+		let reg = Re::parse_regexp(&escape(text)).unwrap();
+		assert!(reg.is_match(text));
+		assert!(!reg.is_match("cafe (u)"));
+	}
+
+	#[test]
+	fn standalone_class() {
+		let class = CharClass::new(vec![('a', 'z'), ('0', '9'), ('_', '_')], false);
+		assert!(parse_class("[a-z0-9_]").unwrap() == Re::Class(class));
+		assert!(parse_class("[^\\d]").unwrap() == Re::Class(CharClass::new(vec![('0', '9')], true)));
+
+		let Err((kind, info)) = parse_class("[ab]c") else { panic!() };
+		assert!(matches!(kind, ReErrorKind::InvalidChar));
+		assert_eq!((info.at, info.end), (4, 5));
+		assert!(matches!(parse_class("a"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(parse_class(""), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(parse_class("[ab"), Err((ReErrorKind::OutOfRange, _))));
+	}
+
+	#[test]
+	fn or_precedence() {
+		let reg = Re::parse_regexp("ab|c").unwrap();
+		assert_eq!(reg.to_postfix(), vec![
+			Token::Char('a'), Token::Char('b'), Token::Concat, Token::Char('c'), Token::Alt
+		]);
+
+		let reg = Re::parse_regexp("a(b|c)d").unwrap();
+		assert_eq!(reg.to_postfix(), vec![
+			Token::Char('a'), Token::Char('b'), Token::Char('c'), Token::Alt, Token::Char('d'), Token::Concat, Token::Concat
+		]);
+	}
+
+	#[test]
+	fn unbalanced_parenthesis() {
+		assert!(matches!(Re::parse_regexp("a)b"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(Re::parse_regexp("(ab"), Err((ReErrorKind::OutOfRange, _))));
+	}
+
+	#[test]
+	fn anchor_stripping() {
+		let reg = Re::parse_regexp("^abc$").unwrap();
+		assert!(reg.strip_anchors() == Re::parse_regexp("abc").unwrap());
+
+		let reg = Re::parse_regexp("^a|b$").unwrap();
+		assert!(reg.strip_anchors() == reg);
+
+		let reg = Re::parse_regexp("^(a|b)c").unwrap();
+		assert!(reg.strip_anchors() == Re::parse_regexp("(a|b)c").unwrap());
+
+		assert!(Re::parse_regexp("^$").unwrap().strip_anchors() == Re::Empty);
+		assert!(Re::parse_regexp("$").unwrap().strip_anchors() == Re::Empty);
+	}
+
+	#[test]
+	fn empty_pattern() {
+		let reg = Re::parse_regexp("").unwrap();
+		assert!(reg == Re::Empty);
+		assert!(reg.is_match(""));
+		assert!(!reg.is_match("a"));
+		assert!(reg.to_postfix() == vec![Token::Empty]);
+	}
+
+	#[test]
+	fn string_conversion() -> Result<(), super::ReError> {
+		let reg: Re = "(a|b)*".parse()?;
+		assert!(reg == Re::parse_regexp("(a|b)*").unwrap());
+
+		let reg = Re::try_from("a+b")?;
+		assert!(reg == Re::parse_regexp("a+b").unwrap());
+
+		assert!(matches!("a{3,1}".parse::<Re>(), Err((ReErrorKind::InvalidRange, _))));
+		assert!(matches!(Re::try_from("(a"), Err((ReErrorKind::OutOfRange, _))));
+		Ok(())
+	}
+
+	fn class(pattern: &str) -> CharClass {
+		match Re::parse_regexp(pattern) {
+			Ok(Re::Class(class)) => class,
+			_ => panic!("{pattern} is not a class")
+		}
+	}
+
+	#[test]
+	fn class_parsing() {
+		assert_eq!(class("[a-z]"), CharClass::new(vec![('a', 'z')], false));
+		assert_eq!(class("[^a-c_\\d]"), CharClass::new(vec![('a', 'c'), ('_', '_'), ('0', '9')], true));
+		assert_eq!(class("[.*]"), CharClass::new(vec![('.', '.'), ('*', '*')], false));
+		assert!(matches!(Re::parse_regexp("[a-z"), Err((ReErrorKind::OutOfRange, _))));
+		assert!(matches!(Re::parse_regexp("[]"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(Re::parse_regexp("[\\D]"), Err((ReErrorKind::InvalidChar, _))));
+	}
+
+	#[test]
+	fn class_dash_handling() {
+		assert_eq!(class("[-a]"), CharClass::new(vec![('-', '-'), ('a', 'a')], false));
+		assert_eq!(class("[a-]"), CharClass::new(vec![('a', 'a'), ('-', '-')], false));
+		assert_eq!(class("[a\\-z]"), CharClass::new(vec![('a', 'a'), ('-', '-'), ('z', 'z')], false));
+
+		match Re::parse_regexp("x[z-a]") {
+			Err((ReErrorKind::InvalidRange, info)) => assert_eq!((info.at, info.end), (2, 5)),
+			_ => panic!()
+		}
+		assert!(matches!(Re::parse_regexp("[a-\\d]"), Err((ReErrorKind::InvalidRange, _))));
+	}
+
+	#[test]
+	fn repeated_anchor() {
+		assert!(matches!(Re::parse_regexp("^*"), Err((ReErrorKind::RepeatedAnchor, _))));
+		assert!(matches!(Re::parse_regexp("a$+"), Err((ReErrorKind::RepeatedAnchor, _))));
+		assert!(matches!(Re::parse_regexp("(^)*a"), Err((ReErrorKind::RepeatedAnchor, _))));
+		assert!(matches!(Re::parse_regexp("\\z{1,2}"), Err((ReErrorKind::RepeatedAnchor, _))));
+
+		// an anchor inside a group that also consumes chars is fine
+		assert!(Re::parse_regexp("(^a)*").is_ok());
+	}
+
+	#[test]
+	fn error_display() {
+		assert_eq!(format!("{}", ReErrorKind::InvalidRange), "invalid range");
+		assert_eq!(format!("{}", ReErrorKind::NonAsciiChar), "non-ASCII character");
+
+		let (kind, info) = Re::parse_regexp("a{3,1}").err().unwrap();
+		assert_eq!(format!("{kind}: {info}"), "invalid range: left number should be lower than or equal to the right one at 1..5");
+	}
+
+	#[test]
+	fn nothing_to_repeat() {
+		for (pattern, at) in [("*a", 0), ("+a", 0), ("a|*b", 2), ("a(+)", 2)] {
+			match Re::parse_regexp(pattern) {
+				Err((ReErrorKind::BadSplit, info)) => {
+					assert_eq!((info.at, info.end), (at, at+1));
+					assert_eq!(info.msg, "nothing to repeat");
+				},
+				_ => panic!("{pattern} should not parse")
+			}
+		}
+	}
+
+	#[test]
+	fn trimmed_pattern() {
+		let trim = ReFlags{trim: true, ..ReFlags::default()};
+		assert!(Re::parse_regexp_with_flags("  abc  ", &trim).unwrap() == Re::parse_regexp("abc").unwrap());
+		assert!(Re::parse_regexp_with_flags("a|b\n", &trim).unwrap() == Re::parse_regexp("a|b").unwrap());
+		assert!(Re::parse_regexp_with_flags(" a b ", &trim).unwrap() == Re::parse_regexp("a b").unwrap());
+		assert!(Re::parse_regexp(" abc").unwrap() != Re::parse_regexp("abc").unwrap());
+
+		let (_, info) = Re::parse_regexp("a{3,1}").err().unwrap();
+		let (_, trimmed) = Re::parse_regexp_with_flags(" \t a{3,1} ", &trim).err().unwrap();
+		assert_eq!((trimmed.at, trimmed.end), (info.at + 3, info.end + 3));
+	}
+
+	#[test]
+	fn unterminated_repeat() {
+		for pattern in ["a{2,", "a{", "a{2", "a{2,3", "(b){1,"] {
+			let Err((kind, info)) = Re::parse_regexp(pattern) else { panic!("{pattern}") };
+			assert!(matches!(kind, ReErrorKind::OutOfRange), "{pattern}");
+			assert_eq!((info.at, info.end), (pattern.find('{').unwrap(), pattern.len()));
+			assert_eq!(info.msg, "Unterminated repetition, expected '}'");
+		}
+		// a '}' further on leaves the bounds to be checked
+		assert!(matches!(Re::parse_regexp("a{2b}"), Err((ReErrorKind::OutOfRange, _))));
+		assert!(matches!(Re::parse_regexp("a{x}"), Err((ReErrorKind::InvalidInt, _))));
+	}
+
+	#[test]
+	fn open_ended_repeat() {
+		let a = || Rc::from(Re::Char('a'));
+		assert!(Re::parse_regexp("a{0,}").unwrap() == Re::Kleen(a()));
+		assert!(Re::parse_regexp("a{1,}").unwrap() == Re::OneOrMore(a()));
+		assert!(Re::parse_regexp("(a){1,}b").unwrap() == Re::parse_regexp("(a)+b").unwrap());
+		assert!(Re::parse_regexp("a{0,}").unwrap().is_match("aaa"));
+
+		let Err((kind, info)) = Re::parse_regexp("a{2,}") else { panic!() };
+		assert!(matches!(kind, ReErrorKind::InvalidInt));
+		assert_eq!((info.at, info.end), (4, 5));
+		assert!(matches!(Re::parse_regexp("a{,}"), Err((ReErrorKind::InvalidInt, _))));
+
+		// the whole repeat is dropped to get past it
+		let (reg, errors) = Re::parse_regexp_collect("a{2,}b");
+		assert_eq!(errors.len(), 1);
+		assert!(reg.unwrap() == Re::parse_regexp("ab").unwrap());
+	}
+
+	#[test]
+	fn quoted_literals() {
+		let reg = Re::parse_regexp("\\Qa.*b\\E").unwrap();
+		assert!(reg == Re::parse_regexp("a\\.\\*b").unwrap());
+		assert!(reg.is_match("a.*b"));
+		assert!(!reg.is_match("axxb"));
+
+		// the rest of the pattern without a \E
+		assert!(Re::parse_regexp("x\\Q|)(?#").unwrap() == Re::parse_regexp("x\\|\\)\\(\\?#").unwrap());
+		// a quantifier after \E repeats the last char
+		assert!(Re::parse_regexp("\\Qab\\E+").unwrap() == Re::parse_regexp("ab+").unwrap());
+		assert!(Re::parse_regexp("(\\Q)\\E)").unwrap() == Re::parse_regexp("(\\))").unwrap());
+		assert!(Re::parse_regexp("a\\Q\\Eb").unwrap().is_match("ab"));
+		assert!(Re::parse_regexp("(\\Q)").is_err());
+	}
+
+	#[test]
+	fn collected_errors() {
+		let ranges = |errors: &[ReError]| errors.iter().map(|(_, info)| (info.at, info.end)).collect::<Vec<_>>();
+
+		let (reg, errors) = Re::parse_regexp_collect("a)b(c");
+		assert!(matches!(errors.as_slice(), [(ReErrorKind::InvalidChar, _), (ReErrorKind::OutOfRange, _)]));
+		// the unmatched ')' and the unterminated group
+		assert_eq!(ranges(&errors), vec![(1, 2), (3, 5)]);
+		assert!(reg.unwrap() == Re::parse_regexp("ab(c)").unwrap());
+
+		let (reg, errors) = Re::parse_regexp_collect("*a{3,1}é[");
+		// the pattern is checked to be ASCII first
+		assert_eq!(ranges(&errors), vec![(7, 8), (0, 1), (2, 6), (9, 10)]);
+		assert!(reg.unwrap() == Re::Char('a'));
+
+		// a bad class range is cut after its start, the class being kept
+		let (reg, errors) = Re::parse_regexp_collect("x[z-a]y");
+		assert!(matches!(errors.as_slice(), [(ReErrorKind::InvalidRange, _)]));
+		assert_eq!(ranges(&errors), vec![(2, 5)]);
+		assert!(reg.unwrap() == Re::parse_regexp("x[z]y").unwrap());
+		let (reg, errors) = Re::parse_regexp_collect("x[z-a]");
+		assert_eq!(ranges(&errors), vec![(2, 5)]);
+		assert!(reg.unwrap() == Re::parse_regexp("x[z]").unwrap());
+		let (reg, errors) = Re::parse_regexp_collect("[a-\\d]");
+		assert_eq!(errors.len(), 1);
+		assert!(reg.unwrap() == Re::parse_regexp("[a]").unwrap());
+
+		let (reg, errors) = Re::parse_regexp_collect("(a|b)");
+		assert!(errors.is_empty());
+		assert!(reg.unwrap() == Re::parse_regexp("(a|b)").unwrap());
+	}
+
+	#[test]
+	fn empty_alternation() {
+		let lenient = ReFlags{allow_empty_alternation: true, ..ReFlags::default()};
+
+		assert!(matches!(Re::parse_regexp("(a|)"), Err((ReErrorKind::InvalidChar, _))));
+		let reg = Re::parse_regexp_with_flags("(a|)", &lenient).unwrap();
+		assert!(reg == Re::Group(Rc::from(Re::Or(Rc::from(Re::Char('a')), Rc::from(Re::Empty))), 1));
+		assert!(reg.is_match("a"));
+		assert!(reg.is_match(""));
+
+		assert!(Re::parse_regexp("|a").is_err());
+		let reg = Re::parse_regexp_with_flags("|a||b|", &lenient).unwrap();
+		for input in ["", "a", "b"] {
+			assert!(reg.is_match(input));
+		}
+		assert!(!reg.is_match("ab"));
+	}
+
+	#[test]
+	fn leading_alternation() {
+		for (pattern, at) in [("|a", 0), ("(|a)", 1), ("a||b", 2), ("(a|(|b))", 4)] {
+			match Re::parse_regexp(pattern) {
+				Err((ReErrorKind::BadSplit, info)) => {
+					assert_eq!((info.at, info.end), (at, at+1));
+					assert_eq!(info.msg, "empty alternation branch before '|'");
+				},
+				_ => panic!("{pattern} should not parse")
+			}
+		}
+	}
+
+	#[test]
+	fn token_spans() {
+		let (reg, tokens) = Re::parse_with_tokens("(a|b)*").unwrap();
+		assert!(reg == Re::parse_regexp("(a|b)*").unwrap());
+
+		let roles: Vec<(Range<usize>, TokenRole)> = tokens.into_iter().map(|token| (token.range, token.role)).collect();
+		assert_eq!(roles, vec![
+			(0..1, TokenRole::GroupOpen),
+			(1..2, TokenRole::Literal),
+			(2..3, TokenRole::Alternation),
+			(3..4, TokenRole::Literal),
+			(4..5, TokenRole::GroupClose),
+			(5..6, TokenRole::Quantifier)
+		]);
+
+		let (_, tokens) = Re::parse_with_tokens("^[a-c]{1,22}\\d.$").unwrap();
+		let roles: Vec<(Range<usize>, TokenRole)> = tokens.into_iter().map(|token| (token.range, token.role)).collect();
+		assert_eq!(roles, vec![
+			(0..1, TokenRole::Anchor),
+			(1..6, TokenRole::Class),
+			(6..12, TokenRole::Quantifier),
+			(12..14, TokenRole::Class),
+			(14..15, TokenRole::Class),
+			(15..16, TokenRole::Anchor)
+		]);
+	}
+
+	#[test]
+	fn comments() {
+		let reg = Re::parse_regexp("a(?#x)b").unwrap();
+		assert!(reg == Re::parse_regexp("ab").unwrap());
+		assert!(reg.is_match("ab"));
+
+		let reg = Re::parse_regexp("(?#start)(a(?#one)(?#two)|b)*(?#end)").unwrap();
+		assert!(reg == Re::parse_regexp("(a|b)*").unwrap());
+
+		match Re::parse_regexp("ab(?#x") {
+			Err((ReErrorKind::OutOfRange, info)) => assert_eq!((info.at, info.end), (2, 5)),
+			_ => panic!("an unterminated comment should not parse")
+		}
+
+		let (_, tokens) = Re::parse_with_tokens("a(?#x)").unwrap();
+		assert_eq!(tokens[1].range, 1..6);
+		assert_eq!(tokens[1].role, TokenRole::Comment);
+	}
+
+	#[test]
+	fn atomic_groups() {
+		let reg = Re::parse_regexp("(?>a*)(b)").unwrap();
+		assert!(reg == and(Re::Atomic(Rc::from(Re::Kleen(Rc::from(Re::Char('a'))))), group(Re::Char('b'), 1)));
+		assert_eq!(reg.group_count(), 1);
+
+		let (_, tokens) = Re::parse_with_tokens("(?>a)").unwrap();
+		assert_eq!(tokens[0].range, 0..3);
+		assert_eq!(tokens[0].role, TokenRole::GroupOpen);
+		assert!(matches!(Re::parse_regexp("(?>a"), Err((ReErrorKind::OutOfRange, _))));
+	}
+
+	#[test]
+	fn named_groups() {
+		let reg = Re::parse_regexp("(?P<year>a)(b)(?<id_2>c)").unwrap();
+		assert!(reg == and(
+			Re::named_group(Re::Char('a'), 1, "year"),
+			and(group(Re::Char('b'), 2), Re::named_group(Re::Char('c'), 3, "id_2"))));
+
+		let (_, tokens) = Re::parse_with_tokens("(?P<y>a)").unwrap();
+		assert_eq!(tokens[0].range, 0..6);
+		assert_eq!(tokens[0].role, TokenRole::GroupOpen);
+
+		assert!(matches!(Re::parse_regexp("(?P<ab"), Err((ReErrorKind::OutOfRange, _))));
+		assert!(matches!(Re::parse_regexp("(?P<a-b>x)"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(Re::parse_regexp("(?P<>x)"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(Re::parse_regexp("(?P<1a>x)"), Err((ReErrorKind::InvalidChar, _))));
 	}
 }
\ No newline at end of file
@@ -0,0 +1,652 @@
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{CharClass, Dfa, Re};
+use super::simplify::merge_ranges;
+
+impl Re {
+	// Direct subexpressions, left to right
+	pub fn children(&self) -> Vec<&Re> {
+		match self {
+			Re::Or(c1, c2) | Re::And(c1, c2) => vec![c1.as_ref(), c2.as_ref()],
+			Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) | Re::Repeat(c, _, _) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => vec![c.as_ref()],
+			Re::Concat(children) | Re::Alt(children) => children.iter().map(|child| child.as_ref()).collect(),
+			_ => Vec::new()
+		}
+	}
+
+	// Index and name of each named group, in opening parenthesis order
+	pub(super) fn group_names(&self) -> Vec<(usize, &str)> {
+		let mut names = Vec::new();
+		if let Re::NamedGroup(_, index, name) = self {
+			names.push((*index, name.as_str()));
+		}
+		for child in self.children() {
+			names.extend(child.group_names());
+		}
+		names
+	}
+
+	// Index and name, if it has one, of each capturing group in opening
+	// parenthesis order
+	pub fn capture_names(&self) -> Vec<(usize, Option<String>)> {
+		let mut names = Vec::new();
+		match self {
+			Re::Group(_, index) => names.push((*index, None)),
+			Re::NamedGroup(_, index, name) => names.push((*index, Some(name.clone()))),
+			_ => ()
+		}
+		for child in self.children() {
+			names.extend(child.capture_names());
+		}
+		names
+	}
+
+	// Number of capturing groups, which is also the highest group index
+	pub fn group_count(&self) -> usize {
+		match self {
+			Re::Group(c, index) | Re::NamedGroup(c, index, _) => c.group_count().max(*index),
+			Re::Atomic(c) => c.group_count(),
+			Re::Or(c1, c2) | Re::And(c1, c2) => c1.group_count().max(c2.group_count()),
+			Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) | Re::Repeat(c, _, _) => c.group_count(),
+			Re::Concat(children) | Re::Alt(children) => children.iter()
+				.map(|child| child.group_count())
+				.max()
+				.unwrap_or(0),
+			_ => 0
+		}
+	}
+
+	pub fn contains_anchor(&self) -> bool {
+		match self {
+			Re::Start | Re::End | Re::TextStart | Re::TextEnd => true,
+			Re::Or(c1, c2) | Re::And(c1, c2) => c1.contains_anchor() || c2.contains_anchor(),
+			Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) | Re::Repeat(c, _, _) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.contains_anchor(),
+			Re::Concat(children) | Re::Alt(children) => children.iter().any(|child| child.contains_anchor()),
+			Re::Empty | Re::Char(_) | Re::AnyChar | Re::Class(_) => false
+		}
+	}
+
+	// Minimum and maximum number of chars a match can span, the maximum being
+	// None when it is unbounded
+	pub fn length_bounds(&self) -> (usize, Option<usize>) {
+		match self {
+			Re::Char(_) | Re::AnyChar | Re::Class(_) => (1, Some(1)),
+			Re::Empty | Re::Start | Re::End | Re::TextStart | Re::TextEnd => (0, Some(0)),
+			Re::Or(c1, c2) => {
+				let (min1, max1) = c1.length_bounds();
+				let (min2, max2) = c2.length_bounds();
+				(min1.min(min2), max1.zip(max2).map(|(a, b)| a.max(b)))
+			},
+			Re::And(c1, c2) => {
+				let (min1, max1) = c1.length_bounds();
+				let (min2, max2) = c2.length_bounds();
+				(min1.saturating_add(min2), max1.zip(max2).and_then(|(a, b)| a.checked_add(b)))
+			},
+			Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.length_bounds(),
+			Re::Concat(children) => children.iter()
+				.map(|child| child.length_bounds())
+				.fold((0, Some(0)), |(min1, max1), (min2, max2)| {
+					(min1.saturating_add(min2), max1.zip(max2).and_then(|(a, b)| a.checked_add(b)))
+				}),
+			Re::Alt(children) => children.iter()
+				.map(|child| child.length_bounds())
+				.reduce(|(min1, max1), (min2, max2)| (min1.min(min2), max1.zip(max2).map(|(a, b)| a.max(b))))
+				.unwrap_or((0, Some(0))),
+			Re::Kleen(_) => (0, None),
+			Re::OneOrMore(c) => (c.length_bounds().0, None),
+			Re::Optional(c) => (0, c.length_bounds().1),
+			Re::Repeat(c, a, b) => {
+				let (min, max) = c.length_bounds();
+				(min.saturating_mul(*a), max.and_then(|max| max.checked_mul(*b)))
+			},
+		}
+	}
+
+	// Whether the regexp matches the empty string, the assertions being taken
+	// as if they held
+	pub fn nullable(&self) -> bool {
+		match self {
+			Re::Empty | Re::Start | Re::End | Re::TextStart | Re::TextEnd => true,
+			Re::Char(_) | Re::AnyChar | Re::Class(_) => false,
+			Re::Kleen(_) | Re::Optional(_) => true,
+			Re::Or(c1, c2) => c1.nullable() || c2.nullable(),
+			Re::And(c1, c2) => c1.nullable() && c2.nullable(),
+			Re::Alt(children) => children.iter().any(|child| child.nullable()),
+			Re::Concat(children) => children.iter().all(|child| child.nullable()),
+			Re::Repeat(c, a, _) => *a == 0 || c.nullable(),
+			Re::OneOrMore(c) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.nullable()
+		}
+	}
+
+	// Chars a non-empty match can start with, as a class that is never
+	// negated, and whether the regexp is nullable
+	pub fn first_chars(&self) -> (CharClass, bool) {
+		let mut ranges = Vec::new();
+		self.push_first_ranges(&mut ranges);
+		(CharClass::new(merge_ranges(ranges), false), self.nullable())
+	}
+
+	fn push_first_ranges(&self, ranges: &mut Vec<(char, char)>) {
+		match self {
+			Re::Char(c) => ranges.push((*c, *c)),
+			// every char but the new line
+			Re::AnyChar => ranges.extend([('\0', '\t'), ('\u{b}', char::MAX)]),
+			Re::Class(class) if class.negated => ranges.extend(complement(class.ranges.clone())),
+			Re::Class(class) => ranges.extend(&class.ranges),
+			Re::Or(c1, c2) => {
+				c1.push_first_ranges(ranges);
+				c2.push_first_ranges(ranges);
+			},
+			Re::Alt(children) => for child in children {
+				child.push_first_ranges(ranges);
+			},
+			Re::And(c1, c2) => {
+				c1.push_first_ranges(ranges);
+				if c1.nullable() {
+					c2.push_first_ranges(ranges);
+				}
+			},
+			Re::Concat(children) => for child in children {
+				child.push_first_ranges(ranges);
+				if !child.nullable() {
+					break;
+				}
+			},
+			Re::Repeat(_, _, 0) => (),
+			Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) | Re::Repeat(c, _, _)
+				| Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.push_first_ranges(ranges),
+			Re::Empty | Re::Start | Re::End | Re::TextStart | Re::TextEnd => ()
+		}
+	}
+
+	// Merged ranges of every char some Char, Class or . of the regexp accepts,
+	// . giving all the chars but '\n' like a negated class gives all those
+	// outside of its ranges
+	pub fn accepting_ranges(&self) -> Vec<(char, char)> {
+		let mut ranges = Vec::new();
+		self.push_ranges(&mut ranges);
+		merge_ranges(ranges)
+	}
+
+	fn push_ranges(&self, ranges: &mut Vec<(char, char)>) {
+		match self {
+			Re::Char(c) => ranges.push((*c, *c)),
+			Re::AnyChar => ranges.extend([('\0', '\t'), ('\u{b}', char::MAX)]),
+			Re::Class(class) if class.negated => ranges.extend(complement(class.ranges.clone())),
+			Re::Class(class) => ranges.extend(&class.ranges),
+			_ => for child in self.children() {
+				child.push_ranges(ranges);
+			}
+		}
+	}
+
+	// Chars that appear in every match, the chars of each concatenated part
+	// and only those shared by all the alternatives of an alternation
+	pub fn required_chars(&self) -> BTreeSet<char> {
+		match self {
+			Re::Char(c) => BTreeSet::from([*c]),
+			Re::Class(class) => match class.ranges.as_slice() {
+				[(low, high)] if low == high && !class.negated => BTreeSet::from([*low]),
+				_ => BTreeSet::new()
+			},
+			Re::And(c1, c2) => &c1.required_chars() | &c2.required_chars(),
+			Re::Concat(children) => children.iter().flat_map(|child| child.required_chars()).collect(),
+			Re::Or(c1, c2) => &c1.required_chars() & &c2.required_chars(),
+			Re::Alt(children) => children.iter()
+				.map(|child| child.required_chars())
+				.reduce(|required, child| &required & &child)
+				.unwrap_or_default(),
+			Re::OneOrMore(c) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.required_chars(),
+			Re::Repeat(c, a, _) if *a > 0 => c.required_chars(),
+			_ => BTreeSet::new()
+		}
+	}
+
+	// Whether every match is a single char, as for a char, . (without the
+	// grapheme cluster flag) and a class
+	pub fn is_single_char_matcher(&self) -> bool {
+		matches!(self, Re::Char(_) | Re::AnyChar | Re::Class(_))
+	}
+
+	// The string matched by the regexp when it is only a concatenation of
+	// chars
+	pub fn as_literal(&self) -> Option<String> {
+		let mut literal = String::new();
+		self.push_literal(&mut literal).then_some(literal)
+	}
+
+	fn push_literal(&self, literal: &mut String) -> bool {
+		match self {
+			Re::Char(c) => {
+				literal.push(*c);
+				true
+			},
+			Re::And(c1, c2) => c1.push_literal(literal) && c2.push_literal(literal),
+			Re::Concat(children) => children.iter().all(|child| child.push_literal(literal)),
+			_ => false
+		}
+	}
+
+	// Heuristic for patterns that take exponential time on a backtracking
+	// matcher: a * or + directly repeating another quantifier, something that
+	// can match the empty string, or alternatives that can start alike.
+	// Running such patterns with the Nfa or Dfa backend avoids the blow up.
+	pub fn has_exponential_risk(&self) -> bool {
+		match self {
+			Re::Kleen(c) | Re::OneOrMore(c) => {
+				let body = without_groups(c);
+				let risky = match body {
+					Re::Kleen(_) | Re::OneOrMore(_) => true,
+					Re::Repeat(_, _, b) => *b > 1,
+					_ => false
+				};
+				risky || body.nullable() || body.has_overlapping_alternatives() || c.has_exponential_risk()
+			},
+			Re::Or(c1, c2) | Re::And(c1, c2) => c1.has_exponential_risk() || c2.has_exponential_risk(),
+			Re::Optional(c) | Re::Repeat(c, _, _) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.has_exponential_risk(),
+			Re::Concat(children) | Re::Alt(children) => children.iter().any(|child| child.has_exponential_risk()),
+			_ => false
+		}
+	}
+
+	// Whether two alternatives of a top-level alternation can start with the
+	// same char
+	fn has_overlapping_alternatives(&self) -> bool {
+		let mut alternatives = Vec::new();
+		self.alternatives(&mut alternatives);
+		let firsts: Vec<Option<&Re>> = alternatives.iter().map(|alternative| alternative.first_atom()).collect();
+
+		firsts.iter().enumerate().any(|(i, a)| firsts[i+1..].iter().any(|b| match (a, b) {
+			(Some(a), Some(b)) => atoms_overlap(a, b),
+			_ => false
+		}))
+	}
+
+	fn alternatives<'a>(&'a self, alternatives: &mut Vec<&'a Re>) {
+		match self {
+			Re::Or(c1, c2) => {
+				c1.alternatives(alternatives);
+				c2.alternatives(alternatives);
+			},
+			Re::Alt(children) => for child in children {
+				child.alternatives(alternatives);
+			},
+			_ => alternatives.push(self)
+		}
+	}
+
+	// Char test the regexp always starts with, if it can be found
+	fn first_atom(&self) -> Option<&Re> {
+		match self {
+			Re::Char(_) | Re::AnyChar | Re::Class(_) => Some(self),
+			Re::And(c, _) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) | Re::OneOrMore(c) => c.first_atom(),
+			Re::Repeat(c, a, _) if *a > 0 => c.first_atom(),
+			Re::Concat(children) => children.first().and_then(|child| child.first_atom()),
+			_ => None
+		}
+	}
+
+	// Number of states of Nfa::compile, counted on the tree without building
+	// the automaton, x{a,b} counting b copies of x. Saturates at usize::MAX.
+	pub fn estimated_nfa_states(&self) -> usize {
+		// and the final match state
+		self.nfa_states().saturating_add(1)
+	}
+
+	fn nfa_states(&self) -> usize {
+		let children = self.children().iter().fold(0, |states: usize, child| states.saturating_add(child.nfa_states()));
+		match self {
+			Re::Empty => 0,
+			Re::Char(_) | Re::AnyChar | Re::Class(_) | Re::Start | Re::End | Re::TextStart | Re::TextEnd => 1,
+			Re::Group(_, _) | Re::NamedGroup(_, _, _) | Re::Atomic(_) | Re::And(_, _) | Re::Concat(_) => children,
+			// one split per alternative but the last, an empty alternation
+			// being a single state that nothing gets through
+			Re::Alt(alternatives) => children.saturating_add(alternatives.len().saturating_sub(1)).max(1),
+			Re::Or(_, _) | Re::Kleen(_) | Re::OneOrMore(_) | Re::Optional(_) => children.saturating_add(1),
+			Re::Repeat(_, a, b) => children.saturating_mul(*b).saturating_add(b - a)
+		}
+	}
+
+	// A shortest string made of the given chars that the regexp does not
+	// match, None if it matches all of them
+	pub fn complement_witness(&self, alphabet: &BTreeSet<char>) -> Option<String> {
+		Dfa::compile(self).shortest_rejected(alphabet)
+	}
+
+	// Whether every string matched by the regexp is matched by other
+	pub fn is_subset(&self, other: &Re) -> bool {
+		Dfa::compile(self).is_subset_of(&Dfa::compile(other))
+	}
+
+	// Number of strings the regexp matches, None if its language is infinite.
+	// Counted on the dfa so that strings matched in several ways are counted
+	// once, saturating at u64::MAX.
+	pub fn language_size(&self) -> Option<u64> {
+		Dfa::compile(self).language_size()
+	}
+
+	pub fn is_finite_language(&self) -> bool {
+		self.language_size().is_some()
+	}
+
+	// Call f with each maximal run of chars of a concatenation, in pattern
+	// order. Any other node breaks the run, and a char outside of a
+	// concatenation is not a run.
+	pub fn for_each_literal_run(&self, f: &mut impl FnMut(&str)) {
+		match self {
+			Re::And(_, _) | Re::Concat(_) => {
+				let mut items = Vec::new();
+				self.chain_items(&mut items);
+
+				let mut run = String::new();
+				for item in items {
+					match item {
+						Re::Char(c) => run.push(*c),
+						_ => {
+							if !run.is_empty() {
+								f(&run);
+								run.clear();
+							}
+							item.for_each_literal_run(f);
+						}
+					}
+				}
+				if !run.is_empty() {
+					f(&run);
+				}
+			},
+			Re::Or(c1, c2) => {
+				c1.for_each_literal_run(f);
+				c2.for_each_literal_run(f);
+			},
+			Re::Alt(children) => for child in children {
+				child.for_each_literal_run(f);
+			},
+			Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) | Re::Repeat(c, _, _) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => c.for_each_literal_run(f),
+			_ => ()
+		}
+	}
+
+	// Operands of nested concatenations, flattened in order
+	fn chain_items<'a>(&'a self, items: &mut Vec<&'a Re>) {
+		match self {
+			Re::And(c1, c2) => {
+				c1.chain_items(items);
+				c2.chain_items(items);
+			},
+			Re::Concat(children) => for child in children {
+				child.chain_items(items);
+			},
+			_ => items.push(self)
+		}
+	}
+}
+
+fn without_groups(reg: &Re) -> &Re {
+	match reg {
+		Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => without_groups(c),
+		_ => reg
+	}
+}
+
+// Chars outside of the ranges, the surrogates never being part of them
+fn complement(ranges: Vec<(char, char)>) -> Vec<(char, char)> {
+	let mut complement = Vec::new();
+	let mut next = Some('\0');
+	for (low, high) in merge_ranges(ranges) {
+		if let Some(start) = next.filter(|start| *start < low) {
+			complement.push((start, prev_char(low)));
+		}
+		next = next_char(high);
+	}
+	if let Some(start) = next {
+		complement.push((start, char::MAX));
+	}
+	complement
+}
+
+pub(super) fn next_char(c: char) -> Option<char> {
+	match c {
+		'\u{d7ff}' => Some('\u{e000}'),
+		_ => char::from_u32(c as u32 + 1)
+	}
+}
+
+// Char right before c, which is never the first one
+fn prev_char(c: char) -> char {
+	match c {
+		'\u{e000}' => '\u{d7ff}',
+		_ => char::from_u32(c as u32 - 1).unwrap()
+	}
+}
+
+// Whether some char is matched by both char tests
+fn atoms_overlap(a: &Re, b: &Re) -> bool {
+	match (a, b) {
+		(Re::Char(c), Re::Char(d)) => c == d,
+		(Re::Char(c), Re::Class(class)) | (Re::Class(class), Re::Char(c)) => class.contains(*c),
+		(Re::Class(c1), Re::Class(c2)) => c1.negated || c2.negated || ranges_overlap(c1, c2),
+		_ => true
+	}
+}
+
+fn ranges_overlap(c1: &CharClass, c2: &CharClass) -> bool {
+	c1.ranges.iter().any(|(low1, high1)| c2.ranges.iter().any(|(low2, high2)| low1 <= high2 && low2 <= high1))
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::collections::BTreeSet;
+	use alloc::string::String;
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use crate::regexp::{CharClass, Nfa, Re};
+
+	fn bounds(pattern: &str) -> (usize, Option<usize>) {
+		Re::parse_regexp(pattern).unwrap().length_bounds()
+	}
+
+	#[test]
+	fn children() {
+		let reg = Re::and(Re::char('a'), Re::char('b'));
+		assert_eq!(reg.children().len(), 2);
+		assert!(*reg.children()[1] == Re::char('b'));
+		assert!(Re::char('a').children().is_empty());
+		assert!(Re::Start.children().is_empty());
+		assert_eq!(Re::parse_regexp("(a)*").unwrap().children().len(), 1);
+		assert_eq!(Re::parse_regexp("a|b|c").unwrap().to_nary().children().len(), 3);
+	}
+
+	#[test]
+	fn group_count() {
+		assert_eq!(Re::parse_regexp("ab").unwrap().group_count(), 0);
+		assert_eq!(Re::parse_regexp("(a)|((b)c)*").unwrap().group_count(), 3);
+	}
+
+	#[test]
+	fn capture_names() {
+		let names = Re::parse_regexp("(?P<y>\\d+)(\\d+)").unwrap().capture_names();
+		assert_eq!(names, vec![(1, Some(String::from("y"))), (2, None)]);
+
+		let names = Re::parse_regexp("((?<a>x)|(?:y))*(?>(z))").unwrap().capture_names();
+		assert_eq!(names, vec![(1, None), (2, Some(String::from("a"))), (3, None)]);
+		assert!(Re::parse_regexp("a(?:b)").unwrap().capture_names().is_empty());
+	}
+
+	#[test]
+	fn anchor_detection() {
+		assert!(Re::parse_regexp("^ab").unwrap().contains_anchor());
+		assert!(Re::parse_regexp("a(b|c\\z)").unwrap().contains_anchor());
+		assert!(!Re::parse_regexp("a(b|c)*").unwrap().contains_anchor());
+	}
+
+	#[test]
+	fn length_bounds() {
+		assert_eq!(bounds("abc"), (3, Some(3)));
+		assert_eq!(bounds("a{2,4}"), (2, Some(4)));
+		assert_eq!(bounds("a*"), (0, None));
+		assert_eq!(bounds("a+"), (1, None));
+		assert_eq!(bounds("(ab)+"), (2, None));
+		assert_eq!(bounds("(ab|c){1,3}"), (1, Some(6)));
+		assert_eq!(bounds("^a.$"), (2, Some(2)));
+		assert_eq!(bounds("a(b*|c)"), (1, None));
+		assert_eq!(bounds("\\d\\w"), (2, Some(2)));
+		assert_eq!(bounds("a?"), (0, Some(1)));
+	}
+
+	#[test]
+	fn nullable() {
+		assert!(Re::parse_regexp("a?").unwrap().nullable());
+		assert!(Re::parse_regexp("(a|b*)c?").unwrap().nullable());
+		assert!(Re::parse_regexp("^a{0,2}$").unwrap().nullable());
+		assert!(!Re::parse_regexp("a?b").unwrap().nullable());
+		assert!(!Re::parse_regexp("(a?b)+").unwrap().nullable());
+	}
+
+	#[test]
+	fn first_chars() {
+		let first = |pattern: &str| Re::parse_regexp(pattern).unwrap().first_chars();
+		assert!(first("a?") == (CharClass::new(vec![('a', 'a')], false), true));
+		assert!(first("a?b") == (CharClass::new(vec![('a', 'b')], false), false));
+		assert!(first("(c|a*)d") == (CharClass::new(vec![('a', 'a'), ('c', 'd')], false), false));
+		assert!(first("^x{0,0}y") == (CharClass::new(vec![('y', 'y')], false), false));
+		assert!(first("[^b-y]") == (CharClass::new(vec![('\0', 'a'), ('z', char::MAX)], false), false));
+
+		let (class, nullable) = first(".*");
+		assert!(nullable && !class.contains('\n') && class.contains('é'));
+	}
+
+	#[test]
+	fn accepting_ranges() {
+		let ranges = |pattern: &str| Re::parse_regexp(pattern).unwrap().accepting_ranges();
+		assert_eq!(ranges("[a-c]x"), vec![('a', 'c'), ('x', 'x')]);
+		assert_eq!(ranges("(b|[a-c])*d?"), vec![('a', 'd')]);
+		assert_eq!(ranges("^$"), vec![]);
+		assert_eq!(ranges("[^\\0-y]z"), vec![('z', char::MAX)]);
+		assert_eq!(ranges(".a"), vec![('\0', '\t'), ('\u{b}', char::MAX)]);
+	}
+
+	#[test]
+	fn nfa_state_estimate() {
+		let estimate = |pattern: &str| Re::parse_regexp(pattern).unwrap().estimated_nfa_states();
+		assert!(estimate("a{0,100}") > 50 * estimate("a*"));
+		for pattern in ["a*", "a{0,100}", "(a|b)+c?", "^[0-9]{2,4}\\.(?:x|y|z)$", "(?>ab)*", "", "a{0,0}"] {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			assert_eq!(reg.estimated_nfa_states(), Nfa::compile(&reg).state_count(), "{pattern}");
+		}
+		let reg = Re::Alt(vec![]);
+		assert_eq!(reg.estimated_nfa_states(), Nfa::compile(&reg).state_count());
+		let reg = Re::parse_regexp("a|b|c").unwrap().to_nary();
+		assert_eq!(reg.estimated_nfa_states(), Nfa::compile(&reg).state_count());
+	}
+
+	#[test]
+	fn complement_witness() {
+		let ab = BTreeSet::from(['a', 'b']);
+		let witness = |pattern: &str| Re::parse_regexp(pattern).unwrap().complement_witness(&ab);
+
+		assert_eq!(witness("a*"), Some("b".into()));
+		assert_eq!(witness("(a|b)*"), None);
+		assert_eq!(witness("a|b|(a|b)(a|b)+"), Some("".into()));
+		assert_eq!(witness("(a|b)*a|b*"), Some("ab".into()));
+		assert_eq!(Re::parse_regexp("x*").unwrap().complement_witness(&BTreeSet::new()), None);
+	}
+
+	#[test]
+	fn subset() {
+		let subset = |a: &str, b: &str| Re::parse_regexp(a).unwrap().is_subset(&Re::parse_regexp(b).unwrap());
+		assert!(subset("abc", "a.c"));
+		assert!(!subset("a.c", "abc"));
+		assert!(subset("(ab)+", "(a|b)*"));
+		assert!(!subset("(a|b)*", "(ab)+"));
+		assert!(subset("[b-d]x", "[a-e]x|y"));
+		assert!(!subset("[b-f]x", "[a-e]x|y"));
+		assert!(subset("a{2,3}", "a+"));
+		assert!(subset("^ab$", "ab"));
+		assert!(!subset("a\\n", "a."));
+		// a negated class matches the new line, which . does not
+		assert!(!subset("[^a]", "."));
+		assert!(subset("[^a\\n]", "."));
+		assert!(!subset("", "a"));
+	}
+
+	#[test]
+	fn language_size() {
+		let size = |pattern: &str| Re::parse_regexp(pattern).unwrap().language_size();
+
+		assert_eq!(size("(a|b)(c|d)"), Some(4));
+		assert_eq!(size("a*"), None);
+		assert_eq!(size("(ab)+"), None);
+		assert_eq!(size("a|a|ab"), Some(2));
+		assert_eq!(size("a{0,1}a{2,3}"), Some(3));
+		assert_eq!(size("[a-z]{2,2}"), Some(676));
+		assert_eq!(size("\\d?x"), Some(11));
+		assert_eq!(size("a^b"), Some(0));
+		// a loop that never reaches an accepting state adds nothing
+		assert_eq!(size("a|b*^"), Some(2));
+		assert_eq!(size(".{8,8}"), Some(u64::MAX));
+		assert!(size("").is_some());
+
+		assert!(Re::parse_regexp("x{1,3}|y").unwrap().is_finite_language());
+		assert!(!Re::parse_regexp("x(y|z*)").unwrap().is_finite_language());
+	}
+
+	#[test]
+	fn literal_runs() {
+		let runs = |pattern: &str| {
+			let mut runs = Vec::new();
+			Re::parse_regexp(pattern).unwrap().for_each_literal_run(&mut |run| runs.push(String::from(run)));
+			runs
+		};
+
+		assert_eq!(runs("ab(c|d)ef"), vec!["ab", "ef"]);
+		assert_eq!(runs("a.bc*d"), vec!["a", "b", "d"]);
+		assert_eq!(runs("x(yz|w)+"), vec!["x", "yz"]);
+		assert_eq!(runs("a|b"), Vec::<String>::new());
+	}
+
+	#[test]
+	fn exponential_risk() {
+		for pattern in ["(a+)+", "(a*)*", "(a|a)*", "x(ab|a)+", "(a{0,2}b*)*", "(\\d|[0-5]x)*", "((a)+)*y"] {
+			assert!(Re::parse_regexp(pattern).unwrap().has_exponential_risk(), "{pattern}");
+		}
+		for pattern in ["(ab)*", "a*b*", "(a|b)*", "([a-c]|[d-f])+", "(a{1,1})*"] {
+			assert!(!Re::parse_regexp(pattern).unwrap().has_exponential_risk(), "{pattern}");
+		}
+	}
+
+	#[test]
+	fn single_char_matchers() {
+		assert!(Re::Char('a').is_single_char_matcher());
+		assert!(Re::parse_regexp("[a-z]").unwrap().is_single_char_matcher());
+		assert!(Re::parse_regexp("\\D").unwrap().is_single_char_matcher());
+		assert!(Re::any_char().is_single_char_matcher());
+		assert!(!Re::parse_regexp("ab").unwrap().is_single_char_matcher());
+		assert!(!Re::parse_regexp("a*").unwrap().is_single_char_matcher());
+		assert!(!Re::parse_regexp("^").unwrap().is_single_char_matcher());
+	}
+
+	#[test]
+	fn literal_patterns() {
+		assert_eq!(Re::parse_regexp("hello").unwrap().as_literal(), Some(String::from("hello")));
+		assert_eq!(Re::parse_regexp("hello").unwrap().to_nary().as_literal(), Some(String::from("hello")));
+		assert_eq!(Re::parse_regexp("x").unwrap().as_literal(), Some(String::from("x")));
+		for pattern in ["a|b", "ab*", "(ab)", "a.c", "^ab"] {
+			assert_eq!(Re::parse_regexp(pattern).unwrap().as_literal(), None, "{pattern}");
+		}
+	}
+
+	#[test]
+	fn required_chars() {
+		let required = |pattern: &str| Re::parse_regexp(pattern).unwrap().required_chars();
+
+		assert_eq!(required("a(b|c)d"), BTreeSet::from(['a', 'd']));
+		assert_eq!(required("a*b"), BTreeSet::from(['b']));
+		assert_eq!(required("(xa|ya)+z{0,2}"), BTreeSet::from(['a']));
+		assert_eq!(required("[q]\\d.b{2,3}"), BTreeSet::from(['q', 'b']));
+		assert_eq!(required("a|b"), BTreeSet::new());
+		assert_eq!(Re::parse_regexp("a(b|c)d").unwrap().to_nary().required_chars(), required("a(b|c)d"));
+	}
+}
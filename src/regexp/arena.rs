@@ -0,0 +1,162 @@
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{CharClass, Re, ReError};
+
+// Index of a node in a ReArena
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+// Node of a regexp stored in an arena, the same as Re with the children
+// referred to by index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArenaNode {
+	Empty,
+	Char(char),
+	Or(NodeId, NodeId),
+	And(NodeId, NodeId),
+	Kleen(NodeId),
+	OneOrMore(NodeId),
+	Optional(NodeId),
+	Repeat(NodeId, usize, usize),
+	AnyChar,
+	Start,
+	End,
+	TextStart,
+	TextEnd,
+	Class(CharClass),
+	Group(NodeId, usize),
+	NamedGroup(NodeId, usize, String),
+	Atomic(NodeId),
+	Concat(Vec<NodeId>),
+	Alt(Vec<NodeId>)
+}
+
+// Nodes of any number of regexps in a single Vec, so that many patterns
+// share one allocation and their nodes are stored next to each other
+#[derive(Debug, Default)]
+pub struct ReArena {
+	nodes: Vec<ArenaNode>
+}
+
+// Parse the pattern and store its nodes in the arena, returning its root
+pub fn parse_into_arena(arena: &mut ReArena, string: &str) -> Result<NodeId, ReError> {
+	Ok(arena.insert(&Re::parse_regexp(string)?))
+}
+
+impl ReArena {
+	pub fn new() -> Self {
+		ReArena::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.nodes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.nodes.is_empty()
+	}
+
+	pub fn node(&self, id: NodeId) -> &ArenaNode {
+		&self.nodes[id.0 as usize]
+	}
+
+	fn push(&mut self, node: ArenaNode) -> NodeId {
+		self.nodes.push(node);
+		NodeId((self.nodes.len() - 1) as u32)
+	}
+
+	// Store the nodes of reg, children before their parent, returning the
+	// id of its root
+	pub fn insert(&mut self, reg: &Re) -> NodeId {
+		let node = match reg {
+			Re::Empty => ArenaNode::Empty,
+			Re::Char(c) => ArenaNode::Char(*c),
+			Re::AnyChar => ArenaNode::AnyChar,
+			Re::Start => ArenaNode::Start,
+			Re::End => ArenaNode::End,
+			Re::TextStart => ArenaNode::TextStart,
+			Re::TextEnd => ArenaNode::TextEnd,
+			Re::Class(class) => ArenaNode::Class(class.clone()),
+			Re::Or(c1, c2) => ArenaNode::Or(self.insert(c1), self.insert(c2)),
+			Re::And(c1, c2) => ArenaNode::And(self.insert(c1), self.insert(c2)),
+			Re::Kleen(c) => ArenaNode::Kleen(self.insert(c)),
+			Re::OneOrMore(c) => ArenaNode::OneOrMore(self.insert(c)),
+			Re::Optional(c) => ArenaNode::Optional(self.insert(c)),
+			Re::Repeat(c, a, b) => ArenaNode::Repeat(self.insert(c), *a, *b),
+			Re::Group(c, index) => ArenaNode::Group(self.insert(c), *index),
+			Re::NamedGroup(c, index, name) => ArenaNode::NamedGroup(self.insert(c), *index, name.clone()),
+			Re::Atomic(c) => ArenaNode::Atomic(self.insert(c)),
+			Re::Concat(children) => ArenaNode::Concat(children.iter().map(|child| self.insert(child)).collect()),
+			Re::Alt(children) => ArenaNode::Alt(children.iter().map(|child| self.insert(child)).collect())
+		};
+		self.push(node)
+	}
+
+	// Direct children of a node, left to right
+	pub fn children(&self, id: NodeId) -> Vec<NodeId> {
+		match self.node(id) {
+			ArenaNode::Or(c1, c2) | ArenaNode::And(c1, c2) => vec![*c1, *c2],
+			ArenaNode::Kleen(c) | ArenaNode::OneOrMore(c) | ArenaNode::Optional(c)
+				| ArenaNode::Repeat(c, _, _) | ArenaNode::Group(c, _) | ArenaNode::NamedGroup(c, _, _) | ArenaNode::Atomic(c) => vec![*c],
+			ArenaNode::Concat(children) | ArenaNode::Alt(children) => children.clone(),
+			_ => Vec::new()
+		}
+	}
+
+	// Regexp rooted at id, to match with any of the backends
+	pub fn to_re(&self, id: NodeId) -> Re {
+		let child = |c: &NodeId| Rc::from(self.to_re(*c));
+		match self.node(id) {
+			ArenaNode::Empty => Re::Empty,
+			ArenaNode::Char(c) => Re::Char(*c),
+			ArenaNode::AnyChar => Re::AnyChar,
+			ArenaNode::Start => Re::Start,
+			ArenaNode::End => Re::End,
+			ArenaNode::TextStart => Re::TextStart,
+			ArenaNode::TextEnd => Re::TextEnd,
+			ArenaNode::Class(class) => Re::Class(class.clone()),
+			ArenaNode::Or(c1, c2) => Re::Or(child(c1), child(c2)),
+			ArenaNode::And(c1, c2) => Re::And(child(c1), child(c2)),
+			ArenaNode::Kleen(c) => Re::Kleen(child(c)),
+			ArenaNode::OneOrMore(c) => Re::OneOrMore(child(c)),
+			ArenaNode::Optional(c) => Re::Optional(child(c)),
+			ArenaNode::Repeat(c, a, b) => Re::Repeat(child(c), *a, *b),
+			ArenaNode::Group(c, index) => Re::Group(child(c), *index),
+			ArenaNode::NamedGroup(c, index, name) => Re::NamedGroup(child(c), *index, name.clone()),
+			ArenaNode::Atomic(c) => Re::Atomic(child(c)),
+			ArenaNode::Concat(children) => Re::Concat(children.iter().map(child).collect()),
+			ArenaNode::Alt(children) => Re::Alt(children.iter().map(child).collect())
+		}
+	}
+
+	pub fn is_match(&self, id: NodeId, input: &str) -> bool {
+		self.to_re(id).is_match(input)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_into_arena, ArenaNode, ReArena};
+
+	#[test]
+	fn arena_parsing() {
+		let mut arena = ReArena::new();
+		let digits = parse_into_arena(&mut arena, "\\d+").unwrap();
+		let word = parse_into_arena(&mut arena, "(a|b)c").unwrap();
+		assert_eq!(arena.len(), 8);
+
+		assert!(matches!(arena.node(word), ArenaNode::And(_, _)));
+		let group = arena.children(word)[0];
+		assert!(matches!(arena.node(group), ArenaNode::Group(_, 1)));
+		assert_eq!(arena.children(arena.children(group)[0]).len(), 2);
+		assert!(arena.children(arena.children(digits)[0]).is_empty());
+
+		assert!(arena.is_match(digits, "123"));
+		assert!(!arena.is_match(digits, "12a"));
+		assert!(arena.is_match(word, "bc"));
+		assert!(!arena.is_match(word, "c"));
+		assert!(parse_into_arena(&mut arena, "(a").is_err());
+	}
+}
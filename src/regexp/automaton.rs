@@ -1,8 +1,916 @@
-pub struct NFA {
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{CharClass, Re, Span};
+use super::analysis::next_char;
 
+// Zero-width conditions on the position. The automata match with the
+// default flags, so ^ and $ are the same as \A and \z.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assertion {
+	Start,
+	End
 }
 
-pub struct DFA {
-	state_count: usize,
-	
-}
\ No newline at end of file
+#[derive(Debug, Clone)]
+enum State {
+	Char(char, usize),
+	Class(CharClass, usize),
+	Any(usize),
+	Split(usize, usize),
+	Epsilon(usize),
+	Assert(Assertion, usize),
+	Match
+}
+
+impl State {
+	// State reached by consuming c, if any
+	fn step(&self, c: char) -> Option<usize> {
+		match self {
+			State::Char(d, next) if *d == c => Some(*next),
+			State::Class(class, next) if class.contains(c) => Some(*next),
+			State::Any(next) if c != '\n' => Some(*next),
+			_ => None
+		}
+	}
+}
+
+// Thompson automaton, with one state per char test and epsilon transitions
+// for the operators
+pub struct Nfa {
+	states: Vec<State>,
+	start: usize,
+	// whether find runs in a single pass, as if the regexp was preceded by
+	// a lazy .*
+	unanchored: bool
+}
+
+impl Nfa {
+	pub fn compile(reg: &Re) -> Nfa {
+		let mut nfa = Nfa{states: vec![State::Match], start: 0, unanchored: false};
+		nfa.start = nfa.compile_node(reg, 0);
+		nfa
+	}
+
+	// Nfa whose search starts a new path at each position of the haystack
+	// while the earlier ones are running, instead of restarting from every
+	// position in turn
+	pub fn compile_unanchored(reg: &Re) -> Nfa {
+		Nfa{unanchored: true, ..Nfa::compile(reg)}
+	}
+
+	pub fn state_count(&self) -> usize {
+		self.states.len()
+	}
+
+	fn push(&mut self, state: State) -> usize {
+		self.states.push(state);
+		self.states.len() - 1
+	}
+
+	// Compile reg so that it continues to the state out, returning its
+	// entry state. Building from the end avoids patching dangling arrows.
+	fn compile_node(&mut self, reg: &Re, out: usize) -> usize {
+		match reg {
+			Re::Empty => out,
+			Re::Char(c) => self.push(State::Char(*c, out)),
+			Re::AnyChar => self.push(State::Any(out)),
+			Re::Class(class) => self.push(State::Class(class.clone(), out)),
+			Re::Start | Re::TextStart => self.push(State::Assert(Assertion::Start, out)),
+			Re::End | Re::TextEnd => self.push(State::Assert(Assertion::End, out)),
+			Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => self.compile_node(c, out),
+			Re::And(c1, c2) => {
+				let mid = self.compile_node(c2, out);
+				self.compile_node(c1, mid)
+			},
+			Re::Concat(children) => children.iter().rev()
+				.fold(out, |next, child| self.compile_node(child, next)),
+			Re::Or(c1, c2) => {
+				let left = self.compile_node(c1, out);
+				let right = self.compile_node(c2, out);
+				self.push(State::Split(left, right))
+			},
+			Re::Alt(children) => {
+				let entries: Vec<usize> = children.iter().map(|child| self.compile_node(child, out)).collect();
+				match entries.split_last() {
+					// an empty class, which nothing gets through
+					None => self.push(State::Class(CharClass::new(Vec::new(), false), out)),
+					Some((last, rest)) => rest.iter().rev()
+						.fold(*last, |next, entry| self.push(State::Split(*entry, next)))
+				}
+			},
+			Re::Kleen(c) => {
+				let split = self.push(State::Split(out, out));
+				let body = self.compile_node(c, split);
+				self.states[split] = State::Split(body, out);
+				split
+			},
+			Re::OneOrMore(c) => {
+				let split = self.push(State::Split(out, out));
+				let body = self.compile_node(c, split);
+				self.states[split] = State::Split(body, out);
+				body
+			},
+			Re::Optional(c) => {
+				let body = self.compile_node(c, out);
+				self.push(State::Split(body, out))
+			},
+			Re::Repeat(c, a, b) => {
+				// c{a,b} is a copies of c followed by b-a nested optional ones
+				let mut entry = out;
+				for _ in *a..*b {
+					let body = self.compile_node(c, entry);
+					entry = self.push(State::Split(body, out));
+				}
+				for _ in 0..*a {
+					entry = self.compile_node(c, entry);
+				}
+				entry
+			},
+		}
+	}
+
+	// Add to set every state reachable from states through epsilon
+	// transitions, crossing only the assertions that hold
+	fn closure(&self, states: &[usize], at_start: bool, at_end: bool) -> Vec<usize> {
+		let mut seen = vec![false; self.states.len()];
+		let mut stack: Vec<usize> = states.to_vec();
+		let mut set = Vec::new();
+
+		while let Some(id) = stack.pop() {
+			if seen[id] {
+				continue;
+			}
+			seen[id] = true;
+			set.push(id);
+
+			match &self.states[id] {
+				State::Split(a, b) => {
+					stack.push(*b);
+					stack.push(*a);
+				},
+				State::Epsilon(next) => stack.push(*next),
+				State::Assert(Assertion::Start, next) if at_start => stack.push(*next),
+				State::Assert(Assertion::End, next) if at_end => stack.push(*next),
+				_ => ()
+			}
+		}
+		set.sort_unstable();
+		set
+	}
+
+	fn step(&self, set: &[usize], c: char) -> Vec<usize> {
+		set.iter().filter_map(|id| self.states[*id].step(c)).collect()
+	}
+
+	fn accepts(&self, set: &[usize]) -> bool {
+		set.iter().any(|id| matches!(self.states[*id], State::Match))
+	}
+
+	// Longest match starting at start, simulating all the paths at once
+	fn longest_at(&self, input: &str, start: usize) -> Option<usize> {
+		let mut set = self.closure(&[self.start], start == 0, start == input.len());
+		let mut end = if self.accepts(&set) { Some(start) } else { None };
+
+		for (i, c) in input[start..].char_indices() {
+			let pos = start + i + c.len_utf8();
+			set = self.closure(&self.step(&set, c), false, pos == input.len());
+			if set.is_empty() {
+				break;
+			}
+			if self.accepts(&set) {
+				end = Some(pos);
+			}
+		}
+		end
+	}
+
+	pub fn is_match(&self, input: &str) -> bool {
+		self.longest_at(input, 0) == Some(input.len())
+	}
+
+	// Full match over chars produced one at a time, looking one char ahead
+	// to know when the end assertions hold
+	pub fn is_match_chars(&self, chars: impl Iterator<Item = char>) -> bool {
+		let mut chars = chars.peekable();
+		let mut set = self.closure(&[self.start], true, chars.peek().is_none());
+		while let Some(c) = chars.next() {
+			set = self.closure(&self.step(&set, c), false, chars.peek().is_none());
+			if set.is_empty() {
+				return false;
+			}
+		}
+		self.accepts(&set)
+	}
+
+	// Length of the longest prefix of the input after which some path of the
+	// automaton is still alive, where the input stops being a possible match
+	pub fn live_prefix(&self, input: &str) -> usize {
+		let mut set = self.closure(&[self.start], true, input.is_empty());
+		if set.is_empty() {
+			return 0;
+		}
+
+		for (i, c) in input.char_indices() {
+			let pos = i + c.len_utf8();
+			set = self.closure(&self.step(&set, c), false, pos == input.len());
+			if set.is_empty() {
+				return i;
+			}
+		}
+		input.len()
+	}
+
+	// Whether some path of the automaton is still alive after the whole
+	// input, which more chars could then turn into a match
+	pub fn is_live(&self, input: &str) -> bool {
+		let mut set = self.closure(&[self.start], true, input.is_empty());
+		for (i, c) in input.char_indices() {
+			let pos = i + c.len_utf8();
+			set = self.closure(&self.step(&set, c), false, pos == input.len());
+		}
+		set.iter().any(|id| match &self.states[*id] {
+			State::Class(class, _) => class.negated || !class.ranges.is_empty(),
+			State::Char(..) | State::Any(_) | State::Match => true,
+			_ => false
+		})
+	}
+
+	// Leftmost-longest search, like Re::find
+	pub fn find(&self, haystack: &str) -> Option<Span> {
+		if self.unanchored {
+			return self.scan(haystack);
+		}
+		haystack.char_indices().map(|(i, _)| i)
+			.chain([haystack.len()])
+			.find_map(|start| self.longest_at(haystack, start).map(|end| Span{start, end}))
+	}
+
+	// Single pass leftmost-longest search, keeping for each state the
+	// earliest position a path reaching it started at
+	fn scan(&self, haystack: &str) -> Option<Span> {
+		let mut starts: Vec<Option<usize>> = vec![None; self.states.len()];
+		let mut best: Option<Span> = None;
+		// a regexp whose paths all begin with ^ only starts at position 0,
+		// which suppresses the implicit prefix
+		let restart = self.closure(&[self.start], false, false).iter()
+			.any(|id| !matches!(self.states[*id], State::Assert(Assertion::Start, _)));
+
+		let mut pos = 0;
+		let mut chars = haystack.chars();
+		loop {
+			if best.is_none() && (pos == 0 || restart) {
+				for id in self.closure(&[self.start], pos == 0, pos == haystack.len()) {
+					starts[id] = starts[id].or(Some(pos));
+				}
+			}
+
+			// the Match state is 0, a later end only wins for the same start
+			if let Some(start) = starts[0] {
+				if best.is_none_or(|span| start < span.start || (start == span.start && pos > span.end)) {
+					best = Some(Span{start, end: pos});
+				}
+			}
+			if let Some(span) = best {
+				starts.iter_mut()
+					.filter(|start| start.is_some_and(|start| start > span.start))
+					.for_each(|start| *start = None);
+				if starts.iter().all(Option::is_none) {
+					break;
+				}
+			}
+
+			let Some(c) = chars.next() else { break };
+			pos += c.len_utf8();
+
+			let mut next: Vec<Option<usize>> = vec![None; self.states.len()];
+			for (id, start) in starts.iter().enumerate() {
+				let (Some(start), Some(target)) = (start, self.states[id].step(c)) else { continue };
+				for id in self.closure(&[target], false, pos == haystack.len()) {
+					if next[id].is_none_or(|other| *start < other) {
+						next[id] = Some(*start);
+					}
+				}
+			}
+			starts = next;
+		}
+		best
+	}
+
+	// Regexp matching the same inputs, built by state elimination: every
+	// state becomes a node of a graph whose arrows are labelled by regexps,
+	// and removing a state relabels each path through it. The result is
+	// equivalent but usually much larger than the regexp compiled.
+	pub fn to_regex(&self) -> Re {
+		// the states, followed by a new entry and a new exit
+		let entry = self.states.len();
+		let exit = entry + 1;
+		let mut arrows: Vec<Vec<Option<Rc<Re>>>> = vec![vec![None; exit + 1]; exit + 1];
+		let add = |arrows: &mut Vec<Vec<Option<Rc<Re>>>>, from: usize, to: usize, label: Re| {
+			let label = match arrows[from][to].take() {
+				Some(other) => Re::Or(other, Rc::from(label)),
+				None => label
+			};
+			arrows[from][to] = Some(Rc::from(label));
+		};
+
+		add(&mut arrows, entry, self.start, Re::Empty);
+		for (id, state) in self.states.iter().enumerate() {
+			match state {
+				State::Char(c, next) => add(&mut arrows, id, *next, Re::Char(*c)),
+				State::Class(class, next) => add(&mut arrows, id, *next, Re::Class(class.clone())),
+				State::Any(next) => add(&mut arrows, id, *next, Re::AnyChar),
+				State::Split(a, b) => {
+					add(&mut arrows, id, *a, Re::Empty);
+					add(&mut arrows, id, *b, Re::Empty);
+				},
+				State::Epsilon(next) => add(&mut arrows, id, *next, Re::Empty),
+				State::Assert(Assertion::Start, next) => add(&mut arrows, id, *next, Re::TextStart),
+				State::Assert(Assertion::End, next) => add(&mut arrows, id, *next, Re::TextEnd),
+				State::Match => add(&mut arrows, id, exit, Re::Empty)
+			}
+		}
+
+		for removed in 0..entry {
+			let looping = arrows[removed][removed].take().filter(|label| !matches!(label.as_ref(), Re::Empty));
+			let incoming: Vec<(usize, Rc<Re>)> = (0..=exit)
+				.filter_map(|from| arrows[from][removed].take().map(|label| (from, label)))
+				.collect();
+			let outgoing: Vec<(usize, Rc<Re>)> = (0..=exit)
+				.filter_map(|to| arrows[removed][to].take().map(|label| (to, label)))
+				.collect();
+
+			for (from, before) in &incoming {
+				let before = match &looping {
+					Some(label) => concat(before.clone(), Rc::from(Re::Kleen(label.clone()))),
+					None => before.clone()
+				};
+				for (to, after) in &outgoing {
+					add(&mut arrows, *from, *to, concat(before.clone(), after.clone()).as_ref().clone());
+				}
+			}
+		}
+
+		match arrows[entry][exit].take() {
+			Some(label) => label.as_ref().clone(),
+			None => Re::Alt(Vec::new())
+		}
+	}
+
+	// Bounds of the intervals of chars that no state of the automaton
+	// tells apart, as the first char of each interval in increasing order
+	fn alphabet(&self) -> Vec<char> {
+		let mut bounds = BTreeSet::from([0, '\n' as u32, '\n' as u32 + 1]);
+		for state in &self.states {
+			match state {
+				State::Char(c, _) => {
+					bounds.insert(*c as u32);
+					bounds.insert(*c as u32 + 1);
+				},
+				State::Class(class, _) => for (low, high) in &class.ranges {
+					bounds.insert(*low as u32);
+					bounds.insert(*high as u32 + 1);
+				},
+				_ => ()
+			}
+		}
+
+		// surrogates are not chars, an interval starting in them starts at
+		// the first char after them
+		let mut alphabet: Vec<char> = bounds.into_iter()
+			.filter_map(|bound| match bound {
+				0xD800..=0xDFFF => Some('\u{E000}'),
+				_ => char::from_u32(bound)
+			})
+			.collect();
+		alphabet.dedup();
+		alphabet
+	}
+}
+
+// Concatenation of two labels of the state elimination, leaving out the
+// empty ones
+fn concat(left: Rc<Re>, right: Rc<Re>) -> Rc<Re> {
+	match (left.as_ref(), right.as_ref()) {
+		(Re::Empty, _) => right,
+		(_, Re::Empty) => left,
+		_ => Rc::from(Re::And(left, right))
+	}
+}
+
+// Deterministic automaton built from an Nfa by the subset construction,
+// with one symbol per interval of equivalent chars
+pub struct Dfa {
+	// first char of each interval of equivalent chars
+	alphabet: Vec<char>,
+	// symbol of each interval, several intervals sharing a catch-all symbol
+	symbols: Vec<usize>,
+	// transitions[state][symbol], None being the dead state
+	transitions: Vec<Vec<Option<usize>>>,
+	// whether a state accepts at the end of the input, where $ holds, and
+	// before it
+	accepting_end: Vec<bool>,
+	accepting_inside: Vec<bool>,
+	// entry state at the start of the input and elsewhere, they differ by
+	// the ^ assertions that hold
+	start: usize,
+	start_inner: usize
+}
+
+impl Dfa {
+	pub fn compile(reg: &Re) -> Dfa {
+		Dfa::from_nfa(&Nfa::compile(reg))
+	}
+
+	pub fn from_nfa(nfa: &Nfa) -> Dfa {
+		let alphabet = nfa.alphabet();
+		let symbols = (0..alphabet.len()).collect();
+		Dfa::build(nfa, alphabet, symbols)
+	}
+
+	// Dfa with a symbol for each char of alphabet, the other chars sharing
+	// catch-all symbols. Chars outside of the alphabet that every state of
+	// the automaton treats the same get the same catch-all, so . over {a,b}
+	// has a single one for all the chars but a, b and '\n', and the
+	// transition table is smaller than the one of compile.
+	pub fn with_alphabet(reg: &Re, alphabet: &BTreeSet<char>) -> Dfa {
+		let nfa = Nfa::compile(reg);
+		let mut bounds: BTreeSet<char> = nfa.alphabet().into_iter().collect();
+		for c in alphabet {
+			bounds.insert(*c);
+			if let Some(next) = next_char(*c) {
+				bounds.insert(next);
+			}
+		}
+		let bounds: Vec<char> = bounds.into_iter().collect();
+
+		// a catch-all is identified by the states that consume its chars
+		let mut catch_alls: BTreeMap<Vec<usize>, usize> = BTreeMap::new();
+		let mut count = alphabet.len();
+		let symbols = bounds.iter().map(|c| match alphabet.iter().position(|d| d == c) {
+			Some(symbol) => symbol,
+			None => {
+				let consumers = (0..nfa.states.len()).filter(|id| nfa.states[*id].step(*c).is_some()).collect();
+				*catch_alls.entry(consumers).or_insert_with(|| {
+					count += 1;
+					count - 1
+				})
+			}
+		}).collect();
+		Dfa::build(&nfa, bounds, symbols)
+	}
+
+	fn build(nfa: &Nfa, alphabet: Vec<char>, symbols: Vec<usize>) -> Dfa {
+		let symbol_count = symbols.iter().max().map_or(0, |max| max + 1);
+		// a char standing for each symbol
+		let mut representatives = vec!['\0'; symbol_count];
+		for (c, symbol) in alphabet.iter().zip(&symbols).rev() {
+			representatives[*symbol] = *c;
+		}
+
+		let mut dfa = Dfa{
+			alphabet,
+			symbols,
+			transitions: Vec::new(),
+			accepting_end: Vec::new(),
+			accepting_inside: Vec::new(),
+			start: 0,
+			start_inner: 0
+		};
+
+		// a subset is identified by its nfa states and whether it is the
+		// entry state at position 0, where ^ holds
+		let mut ids: BTreeMap<(Vec<usize>, bool), usize> = BTreeMap::new();
+		let mut subsets: Vec<(Vec<usize>, bool)> = Vec::new();
+
+		let mut add = |key: (Vec<usize>, bool), subsets: &mut Vec<(Vec<usize>, bool)>| -> usize {
+			*ids.entry(key.clone()).or_insert_with(|| {
+				subsets.push(key);
+				subsets.len() - 1
+			})
+		};
+
+		dfa.start = add((nfa.closure(&[nfa.start], true, false), true), &mut subsets);
+		dfa.start_inner = add((nfa.closure(&[nfa.start], false, false), false), &mut subsets);
+
+		let mut current = 0;
+		while current < subsets.len() {
+			let (set, at_start) = subsets[current].clone();
+
+			// $ only holds at the end of the input, so the subsets never
+			// cross it and it is resolved when deciding acceptance
+			dfa.accepting_end.push(nfa.accepts(&nfa.closure(&set, at_start, true)));
+			dfa.accepting_inside.push(nfa.accepts(&set));
+
+			let steps: Vec<Vec<usize>> = representatives.iter().map(|c| nfa.step(&set, *c)).collect();
+			let row = steps.into_iter().map(|step| {
+				let next = nfa.closure(&step, false, false);
+				if next.is_empty() { None } else { Some(add((next, false), &mut subsets)) }
+			}).collect();
+			dfa.transitions.push(row);
+			current += 1;
+		}
+		dfa
+	}
+
+	pub fn state_count(&self) -> usize {
+		self.transitions.len()
+	}
+
+	pub fn symbol_count(&self) -> usize {
+		self.transitions.first().map_or(0, |row| row.len())
+	}
+
+	fn symbol(&self, c: char) -> usize {
+		self.symbols[self.alphabet.partition_point(|start| *start <= c) - 1]
+	}
+
+	fn accepts(&self, state: usize, input: &str, pos: usize) -> bool {
+		if pos == input.len() {
+			self.accepting_end[state]
+		}
+		else {
+			self.accepting_inside[state]
+		}
+	}
+
+	fn longest_at(&self, input: &str, start: usize) -> Option<usize> {
+		let mut state = if start == 0 { self.start } else { self.start_inner };
+		let mut end = self.accepts(state, input, start).then_some(start);
+
+		for (i, c) in input[start..].char_indices() {
+			let pos = start + i + c.len_utf8();
+			match self.transitions[state][self.symbol(c)] {
+				None => break,
+				Some(next) => state = next
+			}
+			if self.accepts(state, input, pos) {
+				end = Some(pos);
+			}
+		}
+		end
+	}
+
+	pub fn is_match(&self, input: &str) -> bool {
+		self.longest_at(input, 0) == Some(input.len())
+	}
+
+	pub fn find(&self, haystack: &str) -> Option<Span> {
+		haystack.char_indices().map(|(i, _)| i)
+			.chain([haystack.len()])
+			.find_map(|start| self.longest_at(haystack, start).map(|end| Span{start, end}))
+	}
+
+	// Number of chars in the intervals of a symbol, surrogates excluded
+	fn symbol_size(&self, symbol: usize) -> u64 {
+		(0..self.alphabet.len()).filter(|interval| self.symbols[*interval] == symbol).map(|interval| {
+			let start = self.alphabet[interval] as u64;
+			let end = self.alphabet.get(interval + 1).map_or(char::MAX as u64 + 1, |c| *c as u64);
+			let surrogates = end.min(0xE000).saturating_sub(start.max(0xD800));
+			end - start - surrogates
+		}).sum()
+	}
+
+	// Number of inputs matched, None if there are infinitely many of them. A
+	// count that does not fit saturates at u64::MAX. Only the full alphabet
+	// gives the size of each symbol.
+	pub(super) fn language_size(&self) -> Option<u64> {
+		// the states from which some input is accepted
+		let mut live: Vec<bool> = self.accepting_end.clone();
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for state in 0..self.state_count() {
+				if !live[state] && self.transitions[state].iter().flatten().any(|next| live[*next]) {
+					live[state] = true;
+					changed = true;
+				}
+			}
+		}
+
+		// a cycle through live states pumps infinitely many inputs
+		let mut counts: Vec<Option<u64>> = vec![None; self.state_count()];
+		let mut visiting = vec![false; self.state_count()];
+		if live[self.start] { self.count_from(self.start, &live, &mut counts, &mut visiting) } else { Some(0) }
+	}
+
+	fn count_from(&self, state: usize, live: &[bool], counts: &mut Vec<Option<u64>>, visiting: &mut Vec<bool>) -> Option<u64> {
+		if let Some(count) = counts[state] {
+			return Some(count);
+		}
+		if visiting[state] {
+			return None;
+		}
+		visiting[state] = true;
+
+		let mut count = self.accepting_end[state] as u64;
+		for (symbol, next) in self.transitions[state].iter().enumerate() {
+			if let Some(next) = next.filter(|next| live[*next]) {
+				let paths = self.count_from(next, live, counts, visiting)?;
+				count = count.saturating_add(paths.saturating_mul(self.symbol_size(symbol)));
+			}
+		}
+
+		visiting[state] = false;
+		counts[state] = Some(count);
+		Some(count)
+	}
+
+	// Whether every input matched is also matched by other, searching the
+	// product of the two automata for a pair where only this one accepts.
+	// Both must have the full alphabet, the starts of their intervals being
+	// split so that each symbol of the product is a symbol of both.
+	pub(super) fn is_subset_of(&self, other: &Dfa) -> bool {
+		let mut alphabet: Vec<char> = self.alphabet.iter().chain(&other.alphabet).copied().collect();
+		alphabet.sort_unstable();
+		alphabet.dedup();
+
+		let start = (self.start, Some(other.start));
+		let mut visited = BTreeSet::from([start]);
+		let mut queue = VecDeque::from([start]);
+		while let Some((state, other_state)) = queue.pop_front() {
+			if self.accepting_end[state] && !other_state.is_some_and(|other_state| other.accepting_end[other_state]) {
+				return false;
+			}
+			for c in &alphabet {
+				let Some(next) = self.transitions[state][self.symbol(*c)] else { continue };
+				let other_next = other_state.and_then(|other_state| other.transitions[other_state][other.symbol(*c)]);
+				if visited.insert((next, other_next)) {
+					queue.push_back((next, other_next));
+				}
+			}
+		}
+		true
+	}
+
+	// Shortest input over the alphabet that is not matched, trying the chars
+	// in order so the first one found is the smallest of that length. A
+	// breadth first search where None is the dead state.
+	pub fn shortest_rejected(&self, alphabet: &BTreeSet<char>) -> Option<String> {
+		let mut visited = BTreeSet::from([Some(self.start)]);
+		let mut queue = VecDeque::from([(Some(self.start), String::new())]);
+
+		while let Some((state, input)) = queue.pop_front() {
+			match state {
+				Some(state) if self.accepting_end[state] => (),
+				_ => return Some(input)
+			}
+			for c in alphabet {
+				let next = state.and_then(|state| self.transitions[state][self.symbol(*c)]);
+				if visited.insert(next) {
+					let mut longer = input.clone();
+					longer.push(*c);
+					queue.push_back((next, longer));
+				}
+			}
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::collections::BTreeSet;
+	use alloc::format;
+	use alloc::rc::Rc;
+	use alloc::string::String;
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use crate::regexp::{Re, Span};
+	use super::{Dfa, Nfa};
+
+	// xorshift64*, enough to draw reproducible test cases without a
+	// dependency
+	struct Rng(u64);
+
+	impl Rng {
+		fn next(&mut self) -> u64 {
+			self.0 ^= self.0 >> 12;
+			self.0 ^= self.0 << 25;
+			self.0 ^= self.0 >> 27;
+			self.0.wrapping_mul(0x2545F4914F6CDD1D)
+		}
+
+		fn below(&mut self, n: u64) -> u64 {
+			self.next() % n
+		}
+	}
+
+	fn random_re(rng: &mut Rng, depth: usize) -> Re {
+		let leaves = 5;
+		let choice = if depth == 0 { rng.below(leaves) } else { rng.below(leaves + 6) };
+
+		match choice {
+			0 | 1 => Re::char(if rng.below(2) == 0 { 'a' } else { 'b' }),
+			2 => Re::any_char(),
+			3 => Re::class(vec![('b', 'c')], rng.below(2) == 0),
+			4 => if rng.below(2) == 0 { Re::Start } else { Re::End },
+			5 => Re::or(random_re(rng, depth-1), random_re(rng, depth-1)),
+			6 => Re::and(random_re(rng, depth-1), random_re(rng, depth-1)),
+			7 => Re::kleen(random_re(rng, depth-1)),
+			8 => Re::one_or_more(random_re(rng, depth-1)),
+			9 => Re::Optional(Rc::from(random_re(rng, depth-1))),
+			_ => {
+				let min = rng.below(3) as usize;
+				Re::repeat(random_re(rng, depth-1), min, min + rng.below(2) as usize).unwrap()
+			}
+		}
+	}
+
+	fn random_input(rng: &mut Rng) -> String {
+		(0..rng.below(6)).map(|_| ['a', 'b', 'c'][rng.below(3) as usize]).collect()
+	}
+
+	fn agree(reg: &Re, input: &str) -> bool {
+		let expected = reg.is_match(input);
+		Nfa::compile(reg).is_match(input) == expected
+			&& Dfa::compile(reg).is_match(input) == expected
+			&& reg.compile_program().is_match(input) == expected
+	}
+
+	// Smaller regexps to try when shrinking a failing case
+	fn shrink_re(reg: &Re) -> Vec<Re> {
+		match reg {
+			Re::Or(c1, c2) | Re::And(c1, c2) => {
+				let mut smaller = vec![c1.as_ref().clone(), c2.as_ref().clone()];
+				smaller.extend(shrink_re(c1).into_iter().map(|c| rebuild(reg, c, c2.as_ref().clone())));
+				smaller.extend(shrink_re(c2).into_iter().map(|c| rebuild(reg, c1.as_ref().clone(), c)));
+				smaller
+			},
+			Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) => {
+				let mut smaller = vec![c.as_ref().clone()];
+				smaller.extend(shrink_re(c).into_iter().map(|c| match reg {
+					Re::Kleen(_) => Re::kleen(c),
+					Re::OneOrMore(_) => Re::one_or_more(c),
+					_ => Re::Optional(Rc::from(c))
+				}));
+				smaller
+			},
+			Re::Repeat(c, a, b) => {
+				let mut smaller = vec![c.as_ref().clone()];
+				if a > &0 {
+					smaller.push(Re::repeat(c.as_ref().clone(), a-1, *b).unwrap());
+				}
+				if b > a {
+					smaller.push(Re::repeat(c.as_ref().clone(), *a, b-1).unwrap());
+				}
+				smaller.extend(shrink_re(c).into_iter().map(|c| Re::repeat(c, *a, *b).unwrap()));
+				smaller
+			},
+			Re::AnyChar | Re::Class(_) | Re::Start | Re::End => vec![Re::char('a')],
+			_ => Vec::new()
+		}
+	}
+
+	fn rebuild(reg: &Re, left: Re, right: Re) -> Re {
+		match reg {
+			Re::Or(_, _) => Re::or(left, right),
+			_ => Re::and(left, right)
+		}
+	}
+
+	// Greedily replace the case by a smaller failing one until none is left
+	fn shrink(mut reg: Re, mut input: String, fails: impl Fn(&Re, &str) -> bool) -> (Re, String) {
+		loop {
+			let smaller_input = (0..input.len())
+				.map(|i| format!("{}{}", &input[..i], &input[i+1..]))
+				.find(|smaller| fails(&reg, smaller));
+			if let Some(smaller) = smaller_input {
+				input = smaller;
+				continue;
+			}
+
+			match shrink_re(&reg).into_iter().find(|smaller| fails(smaller, &input)) {
+				Some(smaller) => reg = smaller,
+				None => return (reg, input)
+			}
+		}
+	}
+
+	#[test]
+	fn backends_agree_on_random_regexps() {
+		let mut rng = Rng(0x9E3779B97F4A7C15);
+
+		for _ in 0..2000 {
+			let reg = random_re(&mut rng, 3);
+			for _ in 0..8 {
+				let input = random_input(&mut rng);
+				if !agree(&reg, &input) {
+					let (reg, input) = shrink(reg, input, |reg, input| !agree(reg, input));
+					panic!("backends disagree on {} with input {input:?}", reg.to_regex_crate_pattern());
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn symbol_sizes() {
+		let dfa = Dfa::compile(&Re::parse_regexp("[a-c]").unwrap());
+		let sizes: Vec<u64> = (0..dfa.symbol_count()).map(|symbol| dfa.symbol_size(symbol)).collect();
+		assert_eq!(sizes.iter().sum::<u64>(), 0x110000 - 0x800);
+		assert_eq!(sizes[dfa.symbol('a')], 3);
+		assert_eq!(sizes[dfa.symbol('\n')], 1);
+	}
+
+	#[test]
+	fn unanchored_search() {
+		let nfa = Nfa::compile_unanchored(&Re::parse_regexp("b+").unwrap());
+		assert_eq!(nfa.find("aabbb"), Some(Span{start: 2, end: 5}));
+		assert_eq!(nfa.find("abab"), Some(Span{start: 1, end: 2}));
+		assert_eq!(nfa.find("aaa"), None);
+
+		let nfa = Nfa::compile_unanchored(&Re::parse_regexp("^b").unwrap());
+		assert_eq!(nfa.find("ab"), None);
+		assert_eq!(nfa.find("ba"), Some(Span{start: 0, end: 1}));
+
+		let patterns = ["a|ab", "(a|b)*c", "^a|b", "a*", "b$", "(^a|b)c", "x?y{1,2}", ".b|c", "[a-c]+$"];
+		let inputs = ["", "a", "xabx", "bbc", "cabc", "acbc", "xyyy", "aab", "ab\nc", "dcba"];
+		for pattern in patterns {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			let nfa = Nfa::compile_unanchored(&reg);
+			for input in inputs {
+				assert_eq!(nfa.find(input), reg.find(input), "{pattern} on {input:?}");
+			}
+		}
+	}
+
+	// Every input of up to max_len chars of the alphabet
+	fn all_inputs(alphabet: &[char], max_len: usize) -> Vec<String> {
+		let mut inputs = vec![String::new()];
+		let mut last = vec![String::new()];
+		for _ in 0..max_len {
+			last = last.iter()
+				.flat_map(|input| alphabet.iter().map(move |c| format!("{input}{c}")))
+				.collect();
+			inputs.extend(last.iter().cloned());
+		}
+		inputs
+	}
+
+	#[test]
+	fn state_elimination() {
+		let inputs = all_inputs(&['a', 'b', 'c'], 5);
+		for pattern in ["(a|b)*", "a+b?", "(ab|c){1,3}", "^a|b$", "(a*b*)*c", "[^a]b|.c", "a{0,0}", ""] {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			let extracted = Nfa::compile(&reg).to_regex();
+			for input in &inputs {
+				assert_eq!(extracted.is_match(input), reg.is_match(input), "{pattern} on {input:?}");
+			}
+		}
+		assert!(Nfa::compile(&Re::Alt(Vec::new())).to_regex().find("abc").is_none());
+	}
+
+	#[test]
+	fn explicit_alphabet() {
+		let ab = BTreeSet::from(['a', 'b']);
+		let dfa = Dfa::with_alphabet(&Re::parse_regexp(".a").unwrap(), &ab);
+		assert!(dfa.is_match("ba"));
+		assert!(dfa.is_match("xa"));
+		assert!(dfa.is_match("éa"));
+		assert!(!dfa.is_match("ax"));
+		assert!(!dfa.is_match("\na"));
+		// a, b, '\n' and a single catch-all for the chars . matches
+		let full = Dfa::compile(&Re::parse_regexp(".a").unwrap());
+		assert_eq!(dfa.symbol_count(), 4);
+		assert!(dfa.symbol_count() < full.symbol_count());
+		assert!(dfa.state_count() * dfa.symbol_count() < full.state_count() * full.symbol_count());
+
+		// . does not match a newline, even when the alphabet leaves it out
+		let dfa = Dfa::with_alphabet(&Re::parse_regexp(".").unwrap(), &BTreeSet::from(['a']));
+		assert!(!dfa.is_match("\n"));
+		assert!(dfa.is_match("x"));
+
+		// the chars of a class outside of the alphabet
+		let dfa = Dfa::with_alphabet(&Re::parse_regexp("[a-z]").unwrap(), &ab);
+		assert!(dfa.is_match("c"));
+		assert!(!dfa.is_match("C"));
+
+		let reg = Re::parse_regexp("[^a]b|a*").unwrap();
+		let dfa = Dfa::with_alphabet(&reg, &ab);
+		for input in ["", "aa", "bb", "xb", "ab", "x"] {
+			assert_eq!(dfa.is_match(input), reg.is_match(input), "{input:?}");
+		}
+		assert_eq!(dfa.symbol_count(), 3);
+		assert!(dfa.symbol_count() < Dfa::compile(&reg).symbol_count());
+
+		// the catch-alls count all their chars
+		let reg = Re::parse_regexp("[^a]b|.").unwrap();
+		let dfa = Dfa::with_alphabet(&reg, &ab);
+		assert_eq!(dfa.language_size(), Dfa::compile(&reg).language_size());
+		assert!(dfa.language_size().is_some());
+	}
+
+	#[test]
+	fn shrinking_finds_a_minimal_case() {
+		// pretend the backends disagree whenever the input contains a 'b'
+		let reg = Re::and(Re::kleen(Re::or(Re::char('a'), Re::char('b'))), Re::char('c'));
+		let failing = |reg: &Re, input: &str| input.contains('b') && reg.is_match(input);
+
+		let (reg, input) = shrink(reg, String::from("abac"), failing);
+		assert_eq!(input, "bc");
+		assert!(failing(&reg, &input));
+		assert!(shrink_re(&reg).iter().all(|smaller| !failing(smaller, &input)));
+	}
+}
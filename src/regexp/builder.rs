@@ -0,0 +1,125 @@
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::{CharClass, Re, ReError, ReErrorInfo, ReErrorKind};
+
+// Constructors to build a regexp programmatically instead of parsing it
+impl Re {
+	pub fn char(c: char) -> Re {
+		Re::Char(c)
+	}
+
+	pub fn any_char() -> Re {
+		Re::AnyChar
+	}
+
+	pub fn class(ranges: Vec<(char, char)>, negated: bool) -> Re {
+		Re::Class(CharClass::new(ranges, negated))
+	}
+
+	pub fn or(left: Re, right: Re) -> Re {
+		Re::Or(Rc::from(left), Rc::from(right))
+	}
+
+	pub fn and(left: Re, right: Re) -> Re {
+		Re::And(Rc::from(left), Rc::from(right))
+	}
+
+	// Right folded And chain of the regexps, the empty string when there are
+	// none
+	pub fn concat(regs: impl IntoIterator<Item = Re>) -> Re {
+		let items: Vec<Rc<Re>> = regs.into_iter().map(Rc::from).collect();
+		if items.is_empty() { Re::Empty } else { Re::and_chain(&items) }
+	}
+
+	// Right folded Or chain of the regexps, matching nothing when there are
+	// none
+	pub fn alternation(regs: impl IntoIterator<Item = Re>) -> Re {
+		let items: Vec<Rc<Re>> = regs.into_iter().map(Rc::from).collect();
+		if items.is_empty() { Re::never() } else { Re::or_chain(&items) }
+	}
+
+	pub fn kleen(reg: Re) -> Re {
+		Re::Kleen(Rc::from(reg))
+	}
+
+	pub fn one_or_more(reg: Re) -> Re {
+		Re::OneOrMore(Rc::from(reg))
+	}
+
+	// The regexp matching nothing, an alternation without alternatives
+	pub fn never() -> Re {
+		Re::Alt(Vec::new())
+	}
+
+	pub fn optional(reg: Re) -> Re {
+		Re::Optional(Rc::from(reg))
+	}
+
+	// Fails like the parser on reg{min,max} when min > max, the error having
+	// no position in a pattern
+	pub fn repeat(reg: Re, min: usize, max: usize) -> Result<Re, ReError> {
+		if min > max {
+			return Err((ReErrorKind::InvalidRange, ReErrorInfo{at: 0, end: 0, repair: None, msg: String::from("Range start should be lower than or equal to its end")}));
+		}
+		Ok(Re::Repeat(Rc::from(reg), min, max))
+	}
+
+	pub fn group(reg: Re, index: usize) -> Re {
+		Re::Group(Rc::from(reg), index)
+	}
+
+	pub fn named_group(reg: Re, index: usize, name: &str) -> Re {
+		Re::NamedGroup(Rc::from(reg), index, String::from(name))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+	use crate::regexp::{Backend, Matcher, Re, ReErrorKind};
+
+	#[test]
+	fn built_like_parsed() {
+		let reg = Re::and(Re::kleen(Re::group(Re::or(Re::char('a'), Re::char('b')), 1)), Re::char('a'));
+		assert!(reg == Re::parse_regexp("(a|b)*a").unwrap());
+
+		let reg = Re::and(Re::repeat(Re::any_char(), 1, 2).unwrap(), Re::one_or_more(Re::class(vec![('0', '9')], false)));
+		assert!(reg == Re::parse_regexp(".{1,2}\\d+").unwrap());
+
+		let reg = Re::and(Re::char('a'), Re::optional(Re::char('b')));
+		assert!(reg == Re::parse_regexp("ab?").unwrap());
+	}
+
+	#[test]
+	fn joined_lists() {
+		let reg = Re::concat(['a', 'b', 'c'].map(Re::char));
+		assert!(reg.is_match("abc"));
+		assert!(reg == Re::parse_regexp("abc").unwrap());
+		assert!(Re::concat([]) == Re::Empty);
+		assert!(Re::concat([Re::char('a')]) == Re::char('a'));
+
+		let reg = Re::alternation(['a', 'b', 'c'].map(Re::char));
+		assert!(reg == Re::parse_regexp("a|b|c").unwrap());
+		assert!(Re::alternation([]) == Re::never());
+		assert!(!Re::alternation([]).is_match(""));
+	}
+
+	#[test]
+	fn never_matches() {
+		let never = Re::never();
+		for backend in [Backend::Backtracking, Backend::Nfa, Backend::Dfa, Backend::Pike] {
+			let matcher = never.compile(backend);
+			for input in ["", "a", "\n"] {
+				assert!(!matcher.is_match(input), "{backend:?} {input:?}");
+				assert_eq!(matcher.find(input), None, "{backend:?} {input:?}");
+			}
+		}
+	}
+
+	#[test]
+	fn invalid_repeat() {
+		assert!(matches!(Re::repeat(Re::char('a'), 5, 2), Err((ReErrorKind::InvalidRange, _))));
+		assert!(Re::repeat(Re::char('a'), 2, 2).unwrap() == Re::parse_regexp("a{2,2}").unwrap());
+	}
+}
@@ -0,0 +1,201 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::{CharClass, Re, Span};
+
+// Characters the regex crate treats as meta characters (see regex::escape)
+const REGEX_CRATE_META: &str = "\\.+*?()|[]{}^$#&-~";
+
+// Characters that must be escaped inside a regex crate class
+const REGEX_CRATE_CLASS_META: &str = "\\[]-^&~";
+
+impl Re {
+	// Emit a pattern string accepted by the `regex` crate (`regex::Regex::new`)
+	// that matches the same language. Capturing groups keep their numbering,
+	// grouping only needed for precedence is emitted as a non-capturing `(?:...)`.
+	pub fn to_regex_crate_pattern(&self) -> String {
+		self.to_regex_crate_pattern_with_spans().0
+	}
+
+	// Same pattern, with the span each node of the tree is written at. Nodes
+	// are identified by address.
+	pub(super) fn to_regex_crate_pattern_with_spans(&self) -> (String, Vec<(*const Re, Span)>) {
+		let mut pattern = String::new();
+		let mut spans = Vec::new();
+		write_regex_crate(self, &mut pattern, &mut spans);
+		(pattern, spans)
+	}
+}
+
+// Binding strength of a node, a child binding looser than its parent
+// requires must be wrapped in a group
+fn precedence(reg: &Re) -> u8 {
+	match reg {
+		Re::Or(_, _) | Re::Alt(_) => 0,
+		Re::And(_, _) | Re::Concat(_) => 1,
+		Re::Kleen(_) | Re::OneOrMore(_) | Re::Optional(_) | Re::Repeat(_, _, _) => 2,
+		// written as nothing, which is only an atom once grouped
+		Re::Empty => 1,
+		_ => 3
+	}
+}
+
+fn write_child(reg: &Re, min_precedence: u8, pattern: &mut String, spans: &mut Vec<(*const Re, Span)>) {
+	if precedence(reg) < min_precedence {
+		pattern.push_str("(?:");
+		write_regex_crate(reg, pattern, spans);
+		pattern.push(')');
+	}
+	else {
+		write_regex_crate(reg, pattern, spans);
+	}
+}
+
+fn write_class_char(c: char, pattern: &mut String) {
+	if REGEX_CRATE_CLASS_META.contains(c) {
+		pattern.push('\\');
+	}
+	pattern.push(c);
+}
+
+fn write_class(class: &CharClass, pattern: &mut String) {
+	pattern.push('[');
+	if class.negated {
+		pattern.push('^');
+	}
+	for (low, high) in &class.ranges {
+		write_class_char(*low, pattern);
+		if low != high {
+			pattern.push('-');
+			write_class_char(*high, pattern);
+		}
+	}
+	pattern.push(']');
+}
+
+fn write_regex_crate(reg: &Re, pattern: &mut String, spans: &mut Vec<(*const Re, Span)>) {
+	let start = pattern.len();
+	match reg {
+		Re::Empty => (),
+		Re::Char(c) => {
+			if REGEX_CRATE_META.contains(*c) {
+				pattern.push('\\');
+			}
+			pattern.push(*c);
+		},
+		Re::AnyChar => pattern.push('.'),
+		Re::Start => pattern.push('^'),
+		Re::End => pattern.push('$'),
+		Re::TextStart => pattern.push_str("\\A"),
+		Re::TextEnd => pattern.push_str("\\z"),
+		Re::Class(class) => write_class(class, pattern),
+		Re::Group(c, _) => {
+			pattern.push('(');
+			write_regex_crate(c, pattern, spans);
+			pattern.push(')');
+		},
+		// the regex crate has no atomic groups, the group is written as a
+		// plain one
+		Re::Atomic(c) => {
+			pattern.push_str("(?:");
+			write_regex_crate(c, pattern, spans);
+			pattern.push(')');
+		},
+		Re::NamedGroup(c, _, name) => {
+			pattern.push_str(&format!("(?P<{name}>"));
+			write_regex_crate(c, pattern, spans);
+			pattern.push(')');
+		},
+		Re::Or(c1, c2) => {
+			write_child(c1, 0, pattern, spans);
+			pattern.push('|');
+			write_child(c2, 0, pattern, spans);
+		},
+		Re::And(c1, c2) => {
+			write_child(c1, 1, pattern, spans);
+			write_child(c2, 1, pattern, spans);
+		},
+		Re::Alt(children) => {
+			for (i, child) in children.iter().enumerate() {
+				if i > 0 {
+					pattern.push('|');
+				}
+				write_child(child, 0, pattern, spans);
+			}
+		},
+		Re::Concat(children) => {
+			for child in children {
+				write_child(child, 1, pattern, spans);
+			}
+		},
+		// the regex crate rejects stacked repetitions such as a**, so the
+		// operand of a quantifier must be an atom
+		Re::Kleen(c) => {
+			write_child(c, 3, pattern, spans);
+			pattern.push('*');
+		},
+		Re::OneOrMore(c) => {
+			write_child(c, 3, pattern, spans);
+			pattern.push('+');
+		},
+		Re::Optional(c) => {
+			write_child(c, 3, pattern, spans);
+			pattern.push('?');
+		},
+		Re::Repeat(c, a, b) => {
+			write_child(c, 3, pattern, spans);
+			pattern.push_str(&format!("{{{a},{b}}}"));
+		},
+	}
+	spans.push((reg, Span{start, end: pattern.len()}));
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::rc::Rc;
+	use alloc::string::String;
+	use alloc::vec;
+	use crate::regexp::{CharClass, Re};
+
+	fn pattern(string: &str) -> String {
+		Re::parse_regexp(string).unwrap().to_regex_crate_pattern()
+	}
+
+	#[test]
+	fn regex_crate_pattern() {
+		assert_eq!(pattern("(a|b)*a"), "(a|b)*a");
+		assert_eq!(pattern("ab|c"), "ab|c");
+		assert_eq!(pattern("a(b|c)d"), "a(b|c)d");
+		assert_eq!(pattern("(ab)+"), "(ab)+");
+		assert_eq!(pattern("^a.{2,3}$"), "^a.{2,3}$");
+		assert_eq!(pattern("\\d+\\S"), "[0-9]+[^\t-\r ]");
+		assert_eq!(pattern("a=;"), "a=;");
+	}
+
+	#[test]
+	fn regex_crate_precedence() {
+		let a = || Rc::from(Re::Char('a'));
+		let b = || Rc::from(Re::Char('b'));
+
+		let reg = Re::Kleen(Rc::from(Re::Kleen(a())));
+		assert_eq!(reg.to_regex_crate_pattern(), "(?:a*)*");
+
+		let reg = Re::And(Rc::from(Re::Or(a(), b())), a());
+		assert_eq!(reg.to_regex_crate_pattern(), "(?:a|b)a");
+
+		let reg = Re::OneOrMore(Rc::from(Re::And(a(), b())));
+		assert_eq!(reg.to_regex_crate_pattern(), "(?:ab)+");
+
+		let reg = Re::Kleen(Rc::from(Re::Empty));
+		assert_eq!(reg.to_regex_crate_pattern(), "(?:)*");
+	}
+
+	#[test]
+	fn regex_crate_escaping() {
+		let reg = Re::And(Rc::from(Re::Char('.')), Rc::from(Re::Kleen(Rc::from(Re::Char('-')))));
+		assert_eq!(reg.to_regex_crate_pattern(), "\\.\\-*");
+
+		let reg = Re::Class(CharClass::new(vec![('-', '-'), (']', '^')], false));
+		assert_eq!(reg.to_regex_crate_pattern(), "[\\-\\]-\\^]");
+	}
+}
@@ -0,0 +1,126 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::{CharClass, Re, ReError, ReErrorInfo, ReErrorKind};
+
+impl Re {
+	// Translate a shell glob: * matches any run of chars, ? a single char,
+	// [abc], [a-z] and [!abc] are classes and \ makes the next char literal.
+	// Like the whole regexp, a glob matches the whole input.
+	pub fn from_glob(glob: &str) -> Result<Re, ReError> {
+		let mut items = Vec::new();
+		let mut chars = glob.char_indices().peekable();
+
+		while let Some((index, c)) = chars.next() {
+			let item = match c {
+				'*' => Re::kleen(Re::any_char()),
+				'?' => Re::any_char(),
+				'[' => {
+					let (class, end) = parse_glob_class(glob, index)?;
+					while chars.next_if(|(i, _)| *i < end).is_some() {}
+					Re::Class(class)
+				},
+				'\\' => match chars.next() {
+					Some((_, escaped)) => Re::char(escaped),
+					None => return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("Expected a char after '\\'")}))
+				},
+				_ => Re::char(c)
+			};
+			items.push(item);
+		}
+
+		Ok(items.into_iter().rev()
+			.reduce(|right, left| Re::and(left, right))
+			.unwrap_or(Re::Empty))
+	}
+}
+
+// Byte offset right after the ']' closing the class opened at index. A ']'
+// right after the opening bracket and the negation is a literal.
+fn glob_class_end(glob: &str, index: usize) -> Option<usize> {
+	let body = &glob[index+1..];
+	let skip = match body.as_bytes() {
+		[b'!' | b'^', b']', ..] => 2,
+		[b'!' | b'^', ..] | [b']', ..] => 1,
+		_ => 0
+	};
+	body[skip..].find(']').map(|end| index + 1 + skip + end + 1)
+}
+
+// Parse the class opened at index, returning the offset right after it
+fn parse_glob_class(glob: &str, index: usize) -> Result<(CharClass, usize), ReError> {
+	let Some(end) = glob_class_end(glob, index) else {
+		return Err((ReErrorKind::OutOfRange, ReErrorInfo{at: index, end: glob.len(), repair: None, msg: String::from("Unterminated class in glob")}));
+	};
+
+	let mut body: Vec<char> = glob[index+1..end-1].chars().collect();
+	let negated = matches!(body.first(), Some('!' | '^'));
+	if negated {
+		body.remove(0);
+	}
+	if body.is_empty() {
+		return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, end, repair: None, msg: String::from("Expected a char in the class")}));
+	}
+
+	// a '-' at either end of the class is a literal
+	let mut ranges = Vec::new();
+	let mut i = 0;
+	while i < body.len() {
+		if i+2 < body.len() && body[i+1] == '-' {
+			if body[i] > body[i+2] {
+				return Err((ReErrorKind::InvalidRange, ReErrorInfo{at: index, end, repair: None, msg: String::from("Range start should be lower than or equal to its end")}));
+			}
+			ranges.push((body[i], body[i+2]));
+			i += 3;
+		}
+		else {
+			ranges.push((body[i], body[i]));
+			i += 1;
+		}
+	}
+	Ok((CharClass::new(ranges, negated), end))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::regexp::{Re, ReErrorKind};
+
+	fn glob(glob: &str) -> Re {
+		Re::from_glob(glob).unwrap()
+	}
+
+	#[test]
+	fn glob_wildcards() {
+		let reg = glob("a*.txt");
+		assert!(reg.is_match("abc.txt"));
+		assert!(reg.is_match("a.txt"));
+		assert!(!reg.is_match("abc.md"));
+		assert!(!reg.is_match("xa.txt"));
+
+		let reg = glob("f?o");
+		assert!(reg.is_match("foo"));
+		assert!(reg.is_match("féo"));
+		assert!(!reg.is_match("fo"));
+
+		assert!(glob("") == Re::Empty);
+	}
+
+	#[test]
+	fn glob_classes() {
+		let reg = glob("[abc]x[!0-9][]-]");
+		assert!(reg.is_match("bxy]"));
+		assert!(reg.is_match("axz-"));
+		assert!(!reg.is_match("dxy]"));
+		assert!(!reg.is_match("bx1]"));
+
+		assert!(glob("\\*\\?").is_match("*?"));
+		assert!(!glob("\\*").is_match("a"));
+	}
+
+	#[test]
+	fn glob_errors() {
+		assert!(matches!(Re::from_glob("a[bc"), Err((ReErrorKind::OutOfRange, _))));
+		assert!(matches!(Re::from_glob("[!]"), Err((ReErrorKind::OutOfRange, _))));
+		assert!(matches!(Re::from_glob("[z-a]"), Err((ReErrorKind::InvalidRange, _))));
+		assert!(matches!(Re::from_glob("a\\"), Err((ReErrorKind::OutOfRange, _))));
+	}
+}
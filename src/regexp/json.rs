@@ -0,0 +1,432 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+use super::{CharClass, Re, ReError, ReErrorInfo, ReErrorKind};
+
+impl Re {
+	// The tree as a JSON object, each node having a "type" field naming its
+	// variant: {"type":"Or","left":{...},"right":{...}}
+	pub fn to_json(&self) -> String {
+		let mut json = String::new();
+		write_json(self, &mut json);
+		json
+	}
+
+	// Read back the output of to_json, the errors giving the byte range of
+	// the JSON value at fault
+	pub fn from_json(json: &str) -> Result<Re, ReError> {
+		let mut parser = JsonParser{json, pos: 0};
+		let value = parser.value()?;
+		parser.skip_whitespace();
+		if parser.pos < json.len() {
+			return Err(json_error(parser.pos..json.len(), "Unexpected data after the JSON value"));
+		}
+		to_re(&value)
+	}
+}
+
+fn write_string(string: &str, json: &mut String) {
+	json.push('"');
+	for c in string.chars() {
+		match c {
+			'"' => json.push_str("\\\""),
+			'\\' => json.push_str("\\\\"),
+			'\n' => json.push_str("\\n"),
+			'\r' => json.push_str("\\r"),
+			'\t' => json.push_str("\\t"),
+			c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+			c => json.push(c)
+		}
+	}
+	json.push('"');
+}
+
+fn write_char(c: char, json: &mut String) {
+	let mut buffer = [0; 4];
+	write_string(c.encode_utf8(&mut buffer), json);
+}
+
+fn write_json(reg: &Re, json: &mut String) {
+	match reg {
+		Re::Empty => write_node(json, "Empty", "", &[]),
+		Re::AnyChar => write_node(json, "AnyChar", "", &[]),
+		Re::Start => write_node(json, "Start", "", &[]),
+		Re::End => write_node(json, "End", "", &[]),
+		Re::TextStart => write_node(json, "TextStart", "", &[]),
+		Re::TextEnd => write_node(json, "TextEnd", "", &[]),
+		Re::Char(c) => {
+			let mut fields = String::from(",\"char\":");
+			write_char(*c, &mut fields);
+			write_node(json, "Char", &fields, &[]);
+		},
+		Re::Class(class) => {
+			let mut fields = format!(",\"negated\":{},\"ranges\":[", class.negated);
+			for (i, (low, high)) in class.ranges.iter().enumerate() {
+				if i > 0 {
+					fields.push(',');
+				}
+				fields.push('[');
+				write_char(*low, &mut fields);
+				fields.push(',');
+				write_char(*high, &mut fields);
+				fields.push(']');
+			}
+			fields.push(']');
+			write_node(json, "Class", &fields, &[]);
+		},
+		Re::Or(c1, c2) => write_node(json, "Or", "", &[("left", c1), ("right", c2)]),
+		Re::And(c1, c2) => write_node(json, "And", "", &[("left", c1), ("right", c2)]),
+		Re::Kleen(c) => write_node(json, "Kleen", "", &[("inner", c)]),
+		Re::OneOrMore(c) => write_node(json, "OneOrMore", "", &[("inner", c)]),
+		Re::Optional(c) => write_node(json, "Optional", "", &[("inner", c)]),
+		Re::Atomic(c) => write_node(json, "Atomic", "", &[("inner", c)]),
+		Re::Repeat(c, a, b) => write_node(json, "Repeat", &format!(",\"min\":{a},\"max\":{b}"), &[("inner", c)]),
+		Re::Group(c, index) => write_node(json, "Group", &format!(",\"index\":{index}"), &[("inner", c)]),
+		Re::NamedGroup(c, index, name) => {
+			let mut fields = format!(",\"index\":{index},\"name\":");
+			write_string(name, &mut fields);
+			write_node(json, "NamedGroup", &fields, &[("inner", c)]);
+		},
+		Re::Concat(items) | Re::Alt(items) => {
+			let mut fields = String::from(",\"children\":[");
+			for (i, item) in items.iter().enumerate() {
+				if i > 0 {
+					fields.push(',');
+				}
+				write_json(item, &mut fields);
+			}
+			fields.push(']');
+			write_node(json, if matches!(reg, Re::Concat(_)) { "Concat" } else { "Alt" }, &fields, &[]);
+		}
+	}
+}
+
+// Object of the given type, with the already written fields and then the
+// child nodes
+fn write_node(json: &mut String, kind: &str, fields: &str, children: &[(&str, &Rc<Re>)]) {
+	json.push_str(&format!("{{\"type\":\"{kind}\"{fields}"));
+	for (name, child) in children {
+		json.push_str(&format!(",\"{name}\":"));
+		write_json(child, json);
+	}
+	json.push('}');
+}
+
+fn json_error(range: Range<usize>, msg: &str) -> ReError {
+	(ReErrorKind::InvalidChar, ReErrorInfo{at: range.start, end: range.end, repair: None, msg: String::from(msg)})
+}
+
+enum JsonKind {
+	Bool(bool),
+	Number(usize),
+	String(String),
+	Array(Vec<JsonValue>),
+	Object(Vec<(String, JsonValue)>)
+}
+
+// JSON value with the byte range it was read from
+struct JsonValue {
+	kind: JsonKind,
+	range: Range<usize>
+}
+
+impl JsonValue {
+	fn field(&self, name: &str) -> Result<&JsonValue, ReError> {
+		let JsonKind::Object(fields) = &self.kind else {
+			return Err(json_error(self.range.clone(), "Expected an object"));
+		};
+		fields.iter()
+			.find(|(key, _)| key == name)
+			.map(|(_, value)| value)
+			.ok_or_else(|| json_error(self.range.clone(), &format!("Missing field \"{name}\"")))
+	}
+
+	fn as_str(&self) -> Result<&str, ReError> {
+		match &self.kind {
+			JsonKind::String(string) => Ok(string),
+			_ => Err(json_error(self.range.clone(), "Expected a string"))
+		}
+	}
+
+	fn as_char(&self) -> Result<char, ReError> {
+		let mut chars = self.as_str()?.chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) => Ok(c),
+			_ => Err(json_error(self.range.clone(), "Expected a single char"))
+		}
+	}
+
+	fn as_number(&self) -> Result<usize, ReError> {
+		match self.kind {
+			JsonKind::Number(number) => Ok(number),
+			_ => Err(json_error(self.range.clone(), "Expected a number"))
+		}
+	}
+
+	fn as_bool(&self) -> Result<bool, ReError> {
+		match self.kind {
+			JsonKind::Bool(value) => Ok(value),
+			_ => Err(json_error(self.range.clone(), "Expected a boolean"))
+		}
+	}
+
+	fn as_array(&self) -> Result<&[JsonValue], ReError> {
+		match &self.kind {
+			JsonKind::Array(items) => Ok(items),
+			_ => Err(json_error(self.range.clone(), "Expected an array"))
+		}
+	}
+}
+
+fn to_re(value: &JsonValue) -> Result<Re, ReError> {
+	let child = |name: &str| -> Result<Rc<Re>, ReError> { Ok(Rc::from(to_re(value.field(name)?)?)) };
+	let children = || -> Result<Vec<Rc<Re>>, ReError> {
+		value.field("children")?.as_array()?.iter().map(|item| Ok(Rc::from(to_re(item)?))).collect()
+	};
+
+	let kind = value.field("type")?;
+	Ok(match kind.as_str()? {
+		"Empty" => Re::Empty,
+		"AnyChar" => Re::AnyChar,
+		"Start" => Re::Start,
+		"End" => Re::End,
+		"TextStart" => Re::TextStart,
+		"TextEnd" => Re::TextEnd,
+		"Char" => Re::Char(value.field("char")?.as_char()?),
+		"Class" => {
+			let ranges = value.field("ranges")?.as_array()?.iter()
+				.map(|range| match range.as_array()? {
+					[low, high] => Ok((low.as_char()?, high.as_char()?)),
+					_ => Err(json_error(range.range.clone(), "Expected a [low, high] pair"))
+				})
+				.collect::<Result<Vec<_>, ReError>>()?;
+			Re::Class(CharClass::new(ranges, value.field("negated")?.as_bool()?))
+		},
+		"Or" => Re::Or(child("left")?, child("right")?),
+		"And" => Re::And(child("left")?, child("right")?),
+		"Kleen" => Re::Kleen(child("inner")?),
+		"OneOrMore" => Re::OneOrMore(child("inner")?),
+		"Optional" => Re::Optional(child("inner")?),
+		"Atomic" => Re::Atomic(child("inner")?),
+		"Repeat" => {
+			let (min, max) = (value.field("min")?.as_number()?, value.field("max")?.as_number()?);
+			if min > max {
+				return Err((ReErrorKind::InvalidRange, ReErrorInfo{at: value.range.start, end: value.range.end, repair: None, msg: String::from("left number should be lower than or equal to the right one")}));
+			}
+			Re::Repeat(child("inner")?, min, max)
+		},
+		"Group" => Re::Group(child("inner")?, value.field("index")?.as_number()?),
+		"NamedGroup" => Re::NamedGroup(child("inner")?, value.field("index")?.as_number()?, String::from(value.field("name")?.as_str()?)),
+		"Concat" => Re::Concat(children()?),
+		"Alt" => Re::Alt(children()?),
+		_ => return Err(json_error(kind.range.clone(), "Unknown node type"))
+	})
+}
+
+// Reader of the subset of JSON written by to_json: objects, arrays,
+// strings, booleans and non-negative integers
+struct JsonParser<'a> {
+	json: &'a str,
+	pos: usize
+}
+
+impl JsonParser<'_> {
+	fn skip_whitespace(&mut self) {
+		let rest = &self.json[self.pos..];
+		self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+	}
+
+	fn peek(&self) -> Option<u8> {
+		self.json.as_bytes().get(self.pos).copied()
+	}
+
+	fn expect(&mut self, byte: u8) -> Result<(), ReError> {
+		self.skip_whitespace();
+		if self.peek() != Some(byte) {
+			return Err(json_error(self.pos..self.pos+1, &format!("Expected a '{}'", byte as char)));
+		}
+		self.pos += 1;
+		Ok(())
+	}
+
+	fn value(&mut self) -> Result<JsonValue, ReError> {
+		self.skip_whitespace();
+		let start = self.pos;
+		let kind = match self.peek() {
+			Some(b'{') => {
+				self.pos += 1;
+				let mut fields = Vec::new();
+				self.skip_whitespace();
+				if self.peek() == Some(b'}') {
+					self.pos += 1;
+				}
+				else {
+					loop {
+						self.skip_whitespace();
+						let key = self.string()?;
+						self.expect(b':')?;
+						fields.push((key, self.value()?));
+						self.skip_whitespace();
+						match self.peek() {
+							Some(b',') => self.pos += 1,
+							_ => break
+						}
+					}
+					self.expect(b'}')?;
+				}
+				JsonKind::Object(fields)
+			},
+			Some(b'[') => {
+				self.pos += 1;
+				let mut items = Vec::new();
+				self.skip_whitespace();
+				if self.peek() == Some(b']') {
+					self.pos += 1;
+				}
+				else {
+					loop {
+						items.push(self.value()?);
+						self.skip_whitespace();
+						match self.peek() {
+							Some(b',') => self.pos += 1,
+							_ => break
+						}
+					}
+					self.expect(b']')?;
+				}
+				JsonKind::Array(items)
+			},
+			Some(b'"') => JsonKind::String(self.string()?),
+			Some(b'0'..=b'9') => {
+				let digits = self.json[self.pos..].bytes().take_while(u8::is_ascii_digit).count();
+				let number = self.json[self.pos..self.pos+digits].parse()
+					.map_err(|_| (ReErrorKind::InvalidInt, ReErrorInfo{at: start, end: start+digits, repair: None, msg: String::from("Number too large")}))?;
+				self.pos += digits;
+				JsonKind::Number(number)
+			},
+			_ if self.json[self.pos..].starts_with("true") => {
+				self.pos += 4;
+				JsonKind::Bool(true)
+			},
+			_ if self.json[self.pos..].starts_with("false") => {
+				self.pos += 5;
+				JsonKind::Bool(false)
+			},
+			None => return Err(json_error(start..start, "Expected a JSON value")),
+			_ => return Err(json_error(start..start+1, "Expected a JSON value"))
+		};
+		Ok(JsonValue{kind, range: start..self.pos})
+	}
+
+	fn string(&mut self) -> Result<String, ReError> {
+		let start = self.pos;
+		if self.peek() != Some(b'"') {
+			return Err(json_error(start..start+1, "Expected a string"));
+		}
+		self.pos += 1;
+
+		let mut string = String::new();
+		loop {
+			let Some(c) = self.json[self.pos..].chars().next() else {
+				return Err(json_error(start..self.pos, "Unterminated string"));
+			};
+			self.pos += c.len_utf8();
+			match c {
+				'"' => return Ok(string),
+				'\\' => string.push(self.escape()?),
+				c => string.push(c)
+			}
+		}
+	}
+
+	// Char of the escape sequence after a '\'
+	fn escape(&mut self) -> Result<char, ReError> {
+		let start = self.pos - 1;
+		let Some(c) = self.peek() else {
+			return Err(json_error(start..self.pos, "Unterminated string"));
+		};
+		self.pos += 1;
+		Ok(match c {
+			b'"' => '"',
+			b'\\' => '\\',
+			b'/' => '/',
+			b'b' => '\u{8}',
+			b'f' => '\u{C}',
+			b'n' => '\n',
+			b'r' => '\r',
+			b't' => '\t',
+			b'u' => {
+				let high = self.hex_code(start)?;
+				let code = if (0xD800..0xDC00).contains(&high) && self.json[self.pos..].starts_with("\\u") {
+					self.pos += 2;
+					let low = self.hex_code(start)?;
+					0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+				}
+				else {
+					high
+				};
+				char::from_u32(code).ok_or_else(|| json_error(start..self.pos, "Invalid unicode escape"))?
+			},
+			_ => return Err(json_error(start..self.pos, "Invalid escape sequence"))
+		})
+	}
+
+	fn hex_code(&mut self, start: usize) -> Result<u32, ReError> {
+		let digits = self.json.get(self.pos..self.pos+4)
+			.filter(|digits| digits.bytes().all(|c| c.is_ascii_hexdigit()))
+			.ok_or_else(|| json_error(start..self.pos, "Expected four hex digits after '\\u'"))?;
+		self.pos += 4;
+		Ok(u32::from_str_radix(digits, 16).unwrap())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+	use crate::regexp::{Re, ReErrorKind};
+
+	#[test]
+	fn json_round_trip() {
+		let reg = Re::parse_regexp("(a|b)*a").unwrap();
+		assert!(Re::from_json(&reg.to_json()).unwrap() == reg);
+
+		for pattern in ["", "x{2,3}\\d+", "[^a-c\\]]?.$", "^(?P<name>\\A(?>a))\\z", "\"|\\\\|\\t|\\u{1}|\\u{e9}"] {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			assert!(Re::from_json(&reg.to_json()).unwrap() == reg, "{pattern}");
+		}
+		let reg = Re::parse_regexp("a(b|c)d|e").unwrap().to_nary();
+		assert!(Re::from_json(&reg.to_json()).unwrap() == reg);
+		assert!(Re::from_json(&Re::Alt(vec![]).to_json()).unwrap() == Re::Alt(vec![]));
+	}
+
+	#[test]
+	fn json_format() {
+		let reg = Re::parse_regexp("a|\"").unwrap();
+		assert_eq!(reg.to_json(), "{\"type\":\"Or\",\"left\":{\"type\":\"Char\",\"char\":\"a\"},\"right\":{\"type\":\"Char\",\"char\":\"\\\"\"}}");
+		assert_eq!(Re::parse_regexp("\\n{1,2}").unwrap().to_json(),
+			"{\"type\":\"Repeat\",\"min\":1,\"max\":2,\"inner\":{\"type\":\"Char\",\"char\":\"\\n\"}}");
+
+		let json = " { \"type\" : \"Kleen\" , \"inner\" : {\"char\": \"\\u00e9\", \"type\": \"Char\"} } ";
+		assert!(Re::from_json(json).unwrap() == Re::kleen(Re::char('é')));
+		assert!(Re::from_json("{\"type\":\"Char\",\"char\":\"\\ud83d\\ude00\"}").unwrap() == Re::char('😀'));
+	}
+
+	#[test]
+	fn json_errors() {
+		match Re::from_json("{\"type\":\"Kleen\"}") {
+			Err((ReErrorKind::InvalidChar, info)) => assert_eq!((info.at, info.end), (0, 16)),
+			_ => panic!("the inner node is missing")
+		}
+		match Re::from_json("{\"type\":\"Star\"}") {
+			Err((ReErrorKind::InvalidChar, info)) => assert_eq!((info.at, info.end), (8, 14)),
+			_ => panic!("unknown type")
+		}
+		assert!(matches!(Re::from_json("{\"type\":\"Char\",\"char\":\"ab\"}"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(Re::from_json("{\"type\":\"Empty\""), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(Re::from_json("{\"type\":\"Empty\"} x"), Err((ReErrorKind::InvalidChar, _))));
+		let json = "{\"type\":\"Repeat\",\"min\":3,\"max\":1,\"inner\":{\"type\":\"Empty\"}}";
+		assert!(matches!(Re::from_json(json), Err((ReErrorKind::InvalidRange, _))));
+	}
+}
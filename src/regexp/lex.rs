@@ -0,0 +1,144 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::{first_non_ascii, parse_bracket_class, parse_escape, parse_group_name, parse_repeat, skip_comments};
+use super::{CharClass, ParseState, Re, ReError, ReErrorInfo, ReErrorKind};
+
+// Piece of a pattern as cut by lex, before the structure of the pattern is
+// checked. Escapes are resolved, so \. is a Char and \d a Class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lexeme {
+	Char(char),
+	AnyChar,
+	Class(CharClass),
+	Start,
+	End,
+	TextStart,
+	TextEnd,
+	// ( or (?P<name>, the groups that capture
+	GroupOpen(Option<String>),
+	NonCapturingOpen,
+	AtomicOpen,
+	GroupClose,
+	Alternation,
+	Star,
+	Plus,
+	Question,
+	Repeat(usize, usize)
+}
+
+// Lexemes of the pattern, in order and without its comments. The errors are
+// those of the parser for malformed escapes, classes, group names and
+// repeats, a misplaced operator or parenthesis being left to the parser.
+pub fn lex(string: &str) -> Result<Vec<Lexeme>, ReError> {
+	let bytes = string.as_bytes();
+	if let Some(index) = first_non_ascii(bytes) {
+		return Err((ReErrorKind::NonAsciiChar, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("")}));
+	}
+
+	let mut lexemes = Vec::new();
+	let mut index = skip_comments(bytes, 0, &mut ParseState::default())?;
+	let mut quoted = false;
+	while index < bytes.len() {
+		// \Q and \E only switch the chars in between to literals
+		if bytes[index..].starts_with(if quoted { b"\\E" } else { b"\\Q" }) {
+			quoted = !quoted;
+			index += 2;
+			continue;
+		}
+		if quoted {
+			lexemes.push(Lexeme::Char(bytes[index] as char));
+			index += 1;
+			continue;
+		}
+
+		let (lexeme, end) = match bytes[index] {
+			b'.' => (Lexeme::AnyChar, index+1),
+			b'^' => (Lexeme::Start, index+1),
+			b'$' => (Lexeme::End, index+1),
+			b'|' => (Lexeme::Alternation, index+1),
+			b'*' => (Lexeme::Star, index+1),
+			b'+' => (Lexeme::Plus, index+1),
+			b'?' => (Lexeme::Question, index+1),
+			b')' => (Lexeme::GroupClose, index+1),
+			b'{' => {
+				let (min, max, end) = parse_repeat(bytes, index)?;
+				let lexeme = match max {
+					Some(max) => Lexeme::Repeat(min, max),
+					None if min == 0 => Lexeme::Star,
+					None => Lexeme::Plus
+				};
+				(lexeme, end)
+			},
+			b'[' => {
+				let (class, end) = parse_bracket_class(bytes, index)?;
+				(Lexeme::Class(class), end)
+			},
+			b'\\' => {
+				let (reg, end) = parse_escape(bytes, index)?;
+				let lexeme = match reg {
+					Re::Char(c) => Lexeme::Char(c),
+					Re::Class(class) => Lexeme::Class(class),
+					Re::TextStart => Lexeme::TextStart,
+					_ => Lexeme::TextEnd
+				};
+				(lexeme, end)
+			},
+			b'(' if bytes[index..].starts_with(b"(?>") => (Lexeme::AtomicOpen, index+3),
+			b'(' if bytes[index..].starts_with(b"(?:") => (Lexeme::NonCapturingOpen, index+3),
+			b'(' => {
+				let (name, end) = parse_group_name(bytes, index)?;
+				(Lexeme::GroupOpen(name), end)
+			},
+			b'}' | b']' => return Err((ReErrorKind::InvalidChar, ReErrorInfo{at: index, end: index+1, repair: None, msg: String::from("Expected an atom")})),
+			c => (Lexeme::Char(c as char), index+1)
+		};
+		lexemes.push(lexeme);
+		index = skip_comments(bytes, end, &mut ParseState::default())?;
+	}
+	Ok(lexemes)
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::string::String;
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use crate::regexp::{CharClass, ReErrorKind};
+	use super::{lex, Lexeme};
+
+	#[test]
+	fn lexing() {
+		let lexemes = lex("a\\.b*").unwrap();
+		assert_eq!(lexemes, vec![Lexeme::Char('a'), Lexeme::Char('.'), Lexeme::Char('b'), Lexeme::Star]);
+
+		let lexemes = lex("(?P<x>[^a-c]|\\d){1,2}(?:\\A)(?#note)\\t$").unwrap();
+		assert_eq!(lexemes, vec![
+			Lexeme::GroupOpen(Some(String::from("x"))),
+			Lexeme::Class(CharClass::new(vec![('a', 'c')], true)),
+			Lexeme::Alternation,
+			Lexeme::Class(CharClass::new(vec![('0', '9')], false)),
+			Lexeme::GroupClose,
+			Lexeme::Repeat(1, 2),
+			Lexeme::NonCapturingOpen,
+			Lexeme::TextStart,
+			Lexeme::GroupClose,
+			Lexeme::Char('\t'),
+			Lexeme::End
+		]);
+		assert!(lex("").unwrap().is_empty());
+		assert_eq!(lex("a{0,}b{1,}").unwrap(), vec![Lexeme::Char('a'), Lexeme::Star, Lexeme::Char('b'), Lexeme::Plus]);
+
+		let lexemes = lex("\\Q(?#*\\E+").unwrap();
+		assert_eq!(lexemes, "(?#*".chars().map(Lexeme::Char).chain([Lexeme::Plus]).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn lexing_errors() {
+		assert!(matches!(lex("a{2,1}"), Err((ReErrorKind::InvalidRange, _))));
+		assert!(matches!(lex("[ab"), Err((ReErrorKind::OutOfRange, _))));
+		assert!(matches!(lex("\\q"), Err((ReErrorKind::InvalidChar, _))));
+		assert!(matches!(lex("é"), Err((ReErrorKind::NonAsciiChar, _))));
+		// the structure is not checked
+		assert_eq!(lex(")*").unwrap(), vec![Lexeme::GroupClose, Lexeme::Star]);
+	}
+}
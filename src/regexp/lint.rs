@@ -0,0 +1,118 @@
+use alloc::vec::Vec;
+use super::{Re, Span};
+use super::simplify::is_anchor;
+
+// Diagnostic about a valid but suspicious part of a regexp. The span is
+// the byte range of that part in the pattern given by to_regex_crate_pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+	pub span: Span,
+	pub message: &'static str
+}
+
+impl Re {
+	// Diagnostics about the regexp, outer nodes first
+	pub fn lint(&self) -> Vec<Lint> {
+		let (_, spans) = self.to_regex_crate_pattern_with_spans();
+		let mut lints = Vec::new();
+		lint_node(self, &spans, &mut lints);
+		lints
+	}
+}
+
+fn lint_node(reg: &Re, spans: &[(*const Re, Span)], lints: &mut Vec<Lint>) {
+	let mut report = |message| {
+		let (_, span) = spans.iter().find(|(node, _)| core::ptr::eq(*node, reg)).unwrap();
+		lints.push(Lint{span: *span, message});
+	};
+
+	match reg {
+		Re::Repeat(_, _, 0) => report("repeat with zero upper bound"),
+		Re::Kleen(c) | Re::OneOrMore(c) | Re::Repeat(c, _, _) if is_unbounded_repeat(c) => report("nested quantifier may be slow"),
+		Re::Or(c1, c2) if **c1 == Re::Empty || **c2 == Re::Empty => report("empty alternation branch"),
+		Re::Alt(children) if children.iter().any(|child| **child == Re::Empty) => report("empty alternation branch"),
+		Re::And(c1, c2) if doubles_anchor(c1, c2) => report("repeated anchor"),
+		Re::Concat(children) if children.windows(2).any(|pair| doubles_anchor(&pair[0], &pair[1])) => report("repeated anchor"),
+		_ => ()
+	}
+
+	match reg {
+		Re::Or(c1, c2) | Re::And(c1, c2) => {
+			lint_node(c1, spans, lints);
+			lint_node(c2, spans, lints);
+		},
+		Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) | Re::Repeat(c, _, _) | Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => lint_node(c, spans, lints),
+		Re::Concat(children) | Re::Alt(children) => for child in children {
+			lint_node(child, spans, lints);
+		},
+		_ => ()
+	}
+}
+
+// Whether the same anchor ends left and starts right
+fn doubles_anchor(left: &Re, right: &Re) -> bool {
+	let last = match left {
+		Re::And(_, last) => last.as_ref(),
+		Re::Concat(children) => children.last().map_or(left, |last| last.as_ref()),
+		_ => left
+	};
+	let first = match right {
+		Re::And(first, _) => first.as_ref(),
+		Re::Concat(children) => children.first().map_or(right, |first| first.as_ref()),
+		_ => right
+	};
+	is_anchor(last) && last == first
+}
+
+// Whether reg, once its groups are removed, is a * or + repetition
+fn is_unbounded_repeat(reg: &Re) -> bool {
+	match reg {
+		Re::Group(c, _) | Re::NamedGroup(c, _, _) | Re::Atomic(c) => is_unbounded_repeat(c),
+		Re::Kleen(_) | Re::OneOrMore(_) => true,
+		_ => false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::string::String;
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use crate::regexp::{Re, ReFlags, Span};
+
+	// Message and written span of each lint of the pattern
+	fn lints(pattern: &str) -> Vec<(&'static str, String)> {
+		let flags = ReFlags{allow_empty_alternation: true, ..ReFlags::default()};
+		let reg = Re::parse_regexp_with_flags(pattern, &flags).unwrap();
+		let written = reg.to_regex_crate_pattern();
+		reg.lint().iter().map(|lint| (lint.message, String::from(lint.span.as_str(&written)))).collect()
+	}
+
+	#[test]
+	fn zero_repeat() {
+		assert_eq!(lints("xa{0,0}"), vec![("repeat with zero upper bound", String::from("a{0,0}"))]);
+		assert_eq!(lints("a{0,1}"), vec![]);
+
+		let reg = Re::parse_regexp("a{0,0}").unwrap();
+		assert_eq!(reg.lint()[0].span, Span{start: 0, end: 6});
+	}
+
+	#[test]
+	fn nested_quantifier() {
+		assert_eq!(lints("(a*)*"), vec![("nested quantifier may be slow", String::from("(a*)*"))]);
+		assert_eq!(lints("b((a+))+"), vec![("nested quantifier may be slow", String::from("((a+))+"))]);
+		assert_eq!(lints("(ab*)*"), vec![]);
+	}
+
+	#[test]
+	fn repeated_anchor() {
+		assert_eq!(lints("^^a"), vec![("repeated anchor", String::from("^^a"))]);
+		assert_eq!(lints("a$$"), vec![("repeated anchor", String::from("$$"))]);
+		assert_eq!(lints("^a$"), vec![]);
+	}
+
+	#[test]
+	fn empty_branch() {
+		assert_eq!(lints("(a|)"), vec![("empty alternation branch", String::from("a|"))]);
+	}
+}
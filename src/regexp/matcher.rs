@@ -0,0 +1,1140 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ops::Range;
+use super::{Re, ReFlags};
+use super::automaton::{Dfa, Nfa};
+use super::pike::Program;
+
+// Byte range of a match in the searched input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize
+}
+
+// The matchers step a whole char at a time, so the spans they return always
+// lie on char boundaries of the searched input
+impl Span {
+	// Matched text. Panics if the span does not come from this input and
+	// splits one of its chars.
+	pub fn as_str<'a>(&self, input: &'a str) -> &'a str {
+		&input[self.start..self.end]
+	}
+
+	// Matched text, None if the span is out of the input or splits a char
+	pub fn get<'a>(&self, input: &'a str) -> Option<&'a str> {
+		input.get(self.start..self.end)
+	}
+
+	// The span as indices of chars instead of bytes, for display. The chars
+	// before it are counted, so this takes time linear in span.end, and it
+	// panics like as_str.
+	pub fn char_range(&self, input: &str) -> Range<usize> {
+		let start = input[..self.start].chars().count();
+		start..start + self.as_str(input).chars().count()
+	}
+}
+
+// Common interface of the matching backends, all of them following the
+// semantics of Re::is_match and Re::find
+pub trait Matcher {
+	fn is_match(&self, input: &str) -> bool;
+	fn find(&self, input: &str) -> Option<Span>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+	Backtracking,
+	Nfa,
+	Dfa,
+	Pike
+}
+
+impl Matcher for Re {
+	fn is_match(&self, input: &str) -> bool {
+		Re::is_match(self, input)
+	}
+
+	fn find(&self, input: &str) -> Option<Span> {
+		Re::find(self, input)
+	}
+}
+
+impl Matcher for Nfa {
+	fn is_match(&self, input: &str) -> bool {
+		Nfa::is_match(self, input)
+	}
+
+	fn find(&self, input: &str) -> Option<Span> {
+		Nfa::find(self, input)
+	}
+}
+
+impl Matcher for Dfa {
+	fn is_match(&self, input: &str) -> bool {
+		Dfa::is_match(self, input)
+	}
+
+	fn find(&self, input: &str) -> Option<Span> {
+		Dfa::find(self, input)
+	}
+}
+
+impl Matcher for Program {
+	fn is_match(&self, input: &str) -> bool {
+		Program::is_match(self, input)
+	}
+
+	fn find(&self, input: &str) -> Option<Span> {
+		Program::find(self, input)
+	}
+}
+
+// Returned when a match runs out of its step budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExceeded;
+
+// Regexp along with its automata, built once to match many inputs. The ones
+// with an atomic group, which the automata take as a plain group, or whose
+// automata would be too large are matched by backtracking.
+pub struct CompiledRe {
+	re: Re,
+	automata: Option<(Nfa, Program)>
+}
+
+impl CompiledRe {
+	pub fn is_match(&self, input: &str) -> bool {
+		match &self.automata {
+			Some((nfa, _)) => nfa.is_match(input),
+			None => self.re.is_match(input)
+		}
+	}
+
+	pub fn find(&self, haystack: &str) -> Option<Span> {
+		match &self.automata {
+			Some((nfa, _)) => nfa.find(haystack),
+			None => self.re.find(haystack)
+		}
+	}
+
+	pub fn captures(&self, haystack: &str) -> Option<Captures> {
+		match &self.automata {
+			Some((_, program)) => program.run(haystack),
+			None => self.re.captures(haystack)
+		}
+	}
+}
+
+// Spans of the capturing groups of a match, index 0 being the whole match
+// and None a group that did not participate
+pub type Captures = Vec<Option<Span>>;
+
+// Backtracking matcher working on the byte offsets of a str, always
+// stepping a whole char at a time
+struct Backtracker<'a> {
+	input: &'a str,
+	flags: &'a ReFlags,
+	// group spans along the path currently being explored
+	captures: RefCell<Captures>,
+	// number of match_here calls left before the search is aborted
+	budget: Cell<u64>,
+	exceeded: Cell<bool>,
+	// comparison of a char of the pattern with a char of the input
+	char_eq: &'a dyn Fn(char, char) -> bool
+}
+
+fn same_char(c: char, d: char) -> bool {
+	c == d
+}
+
+impl<'a> Backtracker<'a> {
+	fn new(reg: &Re, input: &'a str, flags: &'a ReFlags) -> Self {
+		Self::with_budget(reg, input, flags, u64::MAX)
+	}
+
+	fn with_budget(reg: &Re, input: &'a str, flags: &'a ReFlags, max_steps: u64) -> Self {
+		Backtracker{
+			input,
+			flags,
+			captures: RefCell::new(vec![None; reg.group_count()+1]),
+			budget: Cell::new(max_steps),
+			exceeded: Cell::new(false),
+			char_eq: &same_char
+		}
+	}
+
+	fn next_char(&self, pos: usize) -> Option<char> {
+		self.input[pos..].chars().next()
+	}
+
+	// End of the grapheme cluster starting at pos. This is a best-effort
+	// approximation of the Unicode segmentation rules: a char followed by
+	// combining marks, variation selectors, emoji modifiers and zero width
+	// joined chars.
+	fn grapheme_end(&self, pos: usize) -> usize {
+		let mut end = pos;
+		let mut joined = true;
+
+		for (i, c) in self.input[pos..].char_indices() {
+			if !joined && !is_grapheme_extend(c) {
+				break;
+			}
+			end = pos + i + c.len_utf8();
+			joined = c == '\u{200D}';
+		}
+		end
+	}
+
+	fn at_line_start(&self, pos: usize) -> bool {
+		pos == 0 || (self.flags.multiline && self.input.as_bytes()[pos-1] == b'\n')
+	}
+
+	fn at_line_end(&self, pos: usize) -> bool {
+		pos == self.input.len() || (self.flags.multiline && self.input.as_bytes()[pos] == b'\n')
+	}
+
+	// Try to match reg at pos, calling next with each position the match can
+	// end at, in priority order (greedy quantifiers first). Stops and returns
+	// true as soon as next does. Running out of budget also returns true, to
+	// unwind the search as fast as possible.
+	fn match_here(&self, reg: &Re, pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+		if self.exceeded.get() {
+			return true;
+		}
+		match self.budget.get().checked_sub(1) {
+			Some(left) => self.budget.set(left),
+			None => {
+				self.exceeded.set(true);
+				return true;
+			}
+		}
+
+		match reg {
+			Re::Empty => next(pos),
+			Re::Char(c) => match self.next_char(pos) {
+				Some(d) if (self.char_eq)(*c, d) => next(pos + d.len_utf8()),
+				_ => false
+			},
+			Re::AnyChar => match self.next_char(pos) {
+				Some('\n') if !self.flags.dotall => false,
+				Some(_) if self.flags.grapheme_clusters => next(self.grapheme_end(pos)),
+				Some(d) => next(pos + d.len_utf8()),
+				None => false
+			},
+			Re::Start => self.at_line_start(pos) && next(pos),
+			Re::End => self.at_line_end(pos) && next(pos),
+			Re::TextStart => pos == 0 && next(pos),
+			Re::TextEnd => pos == self.input.len() && next(pos),
+			Re::Class(class) => match self.next_char(pos) {
+				Some(d) if class.contains(d) => next(pos + d.len_utf8()),
+				_ => false
+			},
+			// only the first end of the group is tried, the captures it sets
+			// are undone if the rest of the match fails
+			Re::Atomic(c) => {
+				let saved = self.captures.borrow().clone();
+				let mut first = None;
+				self.match_here(c, pos, &mut |end| {
+					first = Some(end);
+					true
+				});
+				if self.exceeded.get() || first.is_some_and(&mut *next) {
+					return true;
+				}
+				*self.captures.borrow_mut() = saved;
+				false
+			},
+			Re::Group(c, index) | Re::NamedGroup(c, index, _) => self.match_here(c, pos, &mut |end| {
+				let previous = self.captures.borrow()[*index];
+				self.captures.borrow_mut()[*index] = Some(Span{start: pos, end});
+				if next(end) {
+					return true;
+				}
+				self.captures.borrow_mut()[*index] = previous;
+				false
+			}),
+			Re::Or(c1, c2) => {
+				if self.match_here(c1, pos, next) {
+					return true;
+				}
+				self.match_here(c2, pos, next)
+			},
+			Re::And(c1, c2) => self.match_here(c1, pos, &mut |mid| self.match_here(c2, mid, next)),
+			Re::Concat(children) => self.match_sequence(children, pos, next),
+			Re::Alt(children) => children.iter().any(|child| self.match_here(child, pos, next)),
+			Re::Kleen(c) => self.match_repeat(c, 0, None, pos, next),
+			Re::OneOrMore(c) => self.match_repeat(c, 1, None, pos, next),
+			Re::Optional(c) => self.match_repeat(c, 0, Some(1), pos, next),
+			Re::Repeat(c, a, b) => self.match_repeat(c, *a, Some(*b), pos, next),
+		}
+	}
+
+	fn match_sequence(&self, children: &[Rc<Re>], pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+		match children.split_first() {
+			None => next(pos),
+			Some((first, rest)) => self.match_here(first, pos, &mut |mid| self.match_sequence(rest, mid, next))
+		}
+	}
+
+	// Greedily match between min and max (None for unbounded) repetitions of
+	// reg. An iteration that consumes nothing is only accepted while the lower
+	// bound is not reached, so nullable bodies cannot loop forever.
+	fn match_repeat(&self, reg: &Re, min: usize, max: Option<usize>, pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+		if max != Some(0) {
+			let found = self.match_here(reg, pos, &mut |mid| {
+				(mid != pos || min > 0)
+					&& self.match_repeat(reg, min.saturating_sub(1), max.map(|m| m - 1), mid, next)
+			});
+			if found {
+				return true;
+			}
+		}
+		min == 0 && next(pos)
+	}
+}
+
+fn is_grapheme_extend(c: char) -> bool {
+	matches!(c,
+		'\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}'
+		| '\u{20D0}'..='\u{20FF}' | '\u{FE20}'..='\u{FE2F}' | '\u{FE00}'..='\u{FE0F}'
+		| '\u{E0100}'..='\u{E01EF}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{200D}')
+}
+
+impl Backtracker<'_> {
+	// Longest match starting at start, with the group spans of the first
+	// path (in priority order) reaching that end
+	fn longest_at(&self, reg: &Re, start: usize) -> Option<Captures> {
+		let mut best: Option<Captures> = None;
+		self.match_here(reg, start, &mut |pos| {
+			if best.as_ref().is_none_or(|best| pos > best[0].unwrap().end) {
+				let mut captures = self.captures.borrow().clone();
+				captures[0] = Some(Span{start, end: pos});
+				best = Some(captures);
+			}
+			pos == self.input.len()
+		});
+		best
+	}
+
+	// Leftmost-longest match starting at or after from
+	fn search(&self, reg: &Re, from: usize) -> Option<Captures> {
+		self.input[from..].char_indices()
+			.map(|(i, _)| from + i)
+			.chain([self.input.len()])
+			.find_map(|start| self.longest_at(reg, start))
+	}
+}
+
+impl Re {
+	// Build a matcher for the regexp using the given backend. The automata
+	// and Pike backends always match with the default flags.
+	pub fn compile(&self, backend: Backend) -> Box<dyn Matcher> {
+		match backend {
+			Backend::Backtracking => Box::new(self.clone()),
+			Backend::Nfa => Box::new(Nfa::compile_unanchored(self)),
+			Backend::Dfa => Box::new(Dfa::compile(self)),
+			Backend::Pike => Box::new(self.compile_program())
+		}
+	}
+
+	// Automata of the regexp built once, for matching it against many
+	// inputs. Like is_match, it keeps to backtracking past max_nfa_size.
+	pub fn compiled(&self) -> CompiledRe {
+		let supported = !self.contains_atomic() && self.estimated_nfa_states() <= ReFlags::default().max_nfa_size;
+		CompiledRe{
+			re: self.clone(),
+			automata: supported.then(|| (Nfa::compile_unanchored(self), self.compile_program()))
+		}
+	}
+
+	// Leftmost-longest search: the first start position where the regexp
+	// matches, with the longest match at that position whatever the order of
+	// the alternatives. Anchors still hold, ^ only matching at position 0
+	// (or after a newline in multiline mode).
+	pub fn find(&self, haystack: &str) -> Option<Span> {
+		self.find_with_flags(haystack, &ReFlags::default())
+	}
+
+	pub fn find_with_flags(&self, haystack: &str, flags: &ReFlags) -> Option<Span> {
+		// no flag changes how chars match, a literal is best found as a substring
+		if let Some(literal) = self.as_literal() {
+			return haystack.find(&literal).map(|start| Span{start, end: start + literal.len()});
+		}
+		match self.risky_program(flags) {
+			Some(program) => program.find(haystack),
+			None => Backtracker::new(self, haystack, flags).search(self, 0).and_then(|captures| captures[0])
+		}
+	}
+
+	// Longest match starting exactly at the byte offset start, later positions
+	// are not tried. None if start is not a char boundary of the haystack.
+	pub fn find_at(&self, haystack: &str, start: usize) -> Option<Span> {
+		if !haystack.is_char_boundary(start) {
+			return None;
+		}
+		Backtracker::new(self, haystack, &ReFlags::default()).longest_at(self, start)
+			.and_then(|captures| captures[0])
+	}
+
+	// Longest match at the start of the input and the rest of the input after
+	// it, which unlike is_match does not have to consume the whole input
+	pub fn match_prefix<'a>(&self, input: &'a str) -> Option<(Span, &'a str)> {
+		self.find_at(input, 0).map(|span| (span, &input[span.end..]))
+	}
+
+	// Whether the whole input is matched by the regexp
+	pub fn is_match(&self, input: &str) -> bool {
+		self.is_match_with_flags(input, &ReFlags::default())
+	}
+
+	pub fn is_match_with_flags(&self, input: &str, flags: &ReFlags) -> bool {
+		if let Some(literal) = self.as_literal() {
+			return input == literal;
+		}
+		if !flags.grapheme_clusters && !self.length_fits(input) {
+			return false;
+		}
+		if self.prefers_nfa(input, flags) {
+			return Nfa::compile(self).is_match(input);
+		}
+		Backtracker::new(self, input, flags).match_here(self, 0, &mut |pos| pos == input.len())
+	}
+
+	// Whether the number of chars of the input is within length_bounds. A char
+	// is one to four bytes, so the chars are only counted when the byte
+	// length alone does not tell.
+	fn length_fits(&self, input: &str) -> bool {
+		let (min, max) = self.length_bounds();
+		if input.len() < min {
+			return false;
+		}
+		match max {
+			Some(max) if input.len() > max => {
+				let chars = input.chars().count();
+				min <= chars && chars <= max
+			},
+			_ => min <= input.len() / 4 || min <= input.chars().count()
+		}
+	}
+
+	// Whether an Nfa should match the input, which it can only do with the
+	// default matching flags and without atomic groups, that it would take as
+	// plain ones
+	fn prefers_nfa(&self, input: &str, flags: &ReFlags) -> bool {
+		self.automata_match_like(flags) && (input.len() >= flags.nfa_threshold || self.has_exponential_risk())
+	}
+
+	// Whether the automata, which only know the default flags and treat an
+	// atomic group as a plain one, match like the backtracker, and are
+	// small enough to be built
+	fn automata_match_like(&self, flags: &ReFlags) -> bool {
+		let supported = !flags.multiline && !flags.grapheme_clusters && !flags.dotall && !self.contains_atomic();
+		supported && self.estimated_nfa_states() <= flags.max_nfa_size
+	}
+
+	// Pike program used instead of the backtracker for the searches with
+	// captures, when backtracking could take exponential time
+	fn risky_program(&self, flags: &ReFlags) -> Option<Program> {
+		(self.automata_match_like(flags) && self.has_exponential_risk()).then(|| self.compile_program())
+	}
+
+	fn contains_atomic(&self) -> bool {
+		matches!(self, Re::Atomic(_)) || self.children().iter().any(|child| child.contains_atomic())
+	}
+
+	// Same as is_match, a char of the pattern matching the chars of the input
+	// it is equivalent to according to eq(pattern_char, input_char). Classes
+	// and . are not affected.
+	pub fn is_match_with(&self, input: &str, eq: impl Fn(char, char) -> bool) -> bool {
+		let flags = ReFlags::default();
+		let mut backtracker = Backtracker::new(self, input, &flags);
+		backtracker.char_eq = &eq;
+		backtracker.match_here(self, 0, &mut |pos| pos == input.len())
+	}
+
+	// Number of ways the regexp matches the whole input, more than one for an
+	// ambiguous regexp like (a|a). These are the accepting paths of the
+	// backtracking search, where a repetition never takes an empty iteration
+	// it does not need, so the count is finite even for (a*)*.
+	pub fn match_all_splits(&self, input: &str) -> usize {
+		let flags = ReFlags::default();
+		let mut count = 0;
+		Backtracker::new(self, input, &flags).match_here(self, 0, &mut |pos| {
+			if pos == input.len() {
+				count += 1;
+			}
+			false
+		});
+		count
+	}
+
+	// Same as is_match over a stream of chars that never gets collected, like
+	// the output of a decoder. The automaton takes an atomic group as a
+	// plain one, so a regexp with one is matched on the collected chars.
+	pub fn is_match_chars(&self, chars: impl Iterator<Item = char>) -> bool {
+		if self.contains_atomic() {
+			return self.is_match(&chars.collect::<String>());
+		}
+		Nfa::compile(self).is_match_chars(chars)
+	}
+
+	// Length of the longest prefix of the input that can still be extended
+	// into a match, telling where a rejected input went wrong. An atomic
+	// group is taken as a plain one, unlike is_match.
+	pub fn longest_prefix_match(&self, input: &str) -> usize {
+		Nfa::compile(self).live_prefix(input)
+	}
+
+	// Whether the input can still be extended into a match, as an
+	// autocompletion would ask while the text is being typed. An atomic group
+	// is taken as a plain one, unlike is_match.
+	pub fn is_valid_prefix(&self, input: &str) -> bool {
+		Nfa::compile(self).is_live(input)
+	}
+
+	// Same as is_match, giving up after max_steps backtracking steps so that
+	// pathological patterns like (a*)*b cannot run for exponential time
+	pub fn is_match_with_budget(&self, input: &str, max_steps: u64) -> Result<bool, BudgetExceeded> {
+		let flags = ReFlags::default();
+		let backtracker = Backtracker::with_budget(self, input, &flags, max_steps);
+		let found = backtracker.match_here(self, 0, &mut |pos| pos == input.len());
+		if backtracker.exceeded.get() {
+			return Err(BudgetExceeded);
+		}
+		Ok(found)
+	}
+
+	// Group spans of the leftmost-longest match
+	pub fn captures(&self, haystack: &str) -> Option<Captures> {
+		match self.risky_program(&ReFlags::default()) {
+			Some(program) => program.run(haystack),
+			None => Backtracker::new(self, haystack, &ReFlags::default()).search(self, 0)
+		}
+	}
+
+	// Spans of the groups of a match of the whole input, None if no match
+	// ends with the input
+	pub fn fullmatch_captures(&self, input: &str) -> Option<Captures> {
+		Backtracker::new(self, input, &ReFlags::default()).longest_at(self, 0)
+			.filter(|captures| captures[0].unwrap().end == input.len())
+	}
+
+	// Spans of the named groups of the leftmost-longest match by name,
+	// leaving out the groups that did not participate
+	pub fn captures_map(&self, haystack: &str) -> Option<BTreeMap<String, Span>> {
+		let captures = self.captures(haystack)?;
+		Some(self.group_names().into_iter()
+			.filter_map(|(index, name)| captures[index].map(|span| (String::from(name), span)))
+			.collect())
+	}
+
+	// Group spans of every successive non-overlapping match. An empty match
+	// moves the next search one char forward so the iteration always ends.
+	pub fn captures_iter<'a>(&'a self, haystack: &'a str) -> impl Iterator<Item = Captures> + 'a {
+		let mut from = Some(0);
+		let program = self.risky_program(&ReFlags::default());
+
+		core::iter::from_fn(move || {
+			let captures = match &program {
+				Some(program) => program.run_from(haystack, from?)?,
+				None => Backtracker::new(self, haystack, &ReFlags::default()).search(self, from?)?
+			};
+			let span = captures[0].unwrap();
+
+			from = if span.end > span.start {
+				Some(span.end)
+			}
+			else {
+				haystack[span.end..].chars().next().map(|c| span.end + c.len_utf8())
+			};
+			Some(captures)
+		})
+	}
+
+	pub fn find_iter<'a>(&'a self, haystack: &'a str) -> impl Iterator<Item = Span> + 'a {
+		self.captures_iter(haystack).filter_map(|captures| captures[0])
+	}
+
+	// Start and text of every successive non-overlapping match, like
+	// str::match_indices
+	pub fn match_indices(&self, haystack: &str) -> Vec<(usize, String)> {
+		self.find_iter(haystack)
+			.map(|span| (span.start, String::from(span.as_str(haystack))))
+			.collect()
+	}
+
+	// Pieces of the haystack each ending with a match, the text after the
+	// last match being the final piece, like str::split_inclusive
+	pub fn split_inclusive(&self, haystack: &str) -> Vec<String> {
+		let mut pieces = Vec::new();
+		let mut start = 0;
+		for span in self.find_iter(haystack) {
+			if span.end > start {
+				pieces.push(String::from(&haystack[start..span.end]));
+				start = span.end;
+			}
+		}
+		if start < haystack.len() {
+			pieces.push(String::from(&haystack[start..]));
+		}
+		pieces
+	}
+
+	// Copy of the haystack with only the leftmost-longest match replaced, the
+	// haystack itself if there is no match
+	pub fn replace_first(&self, haystack: &str, replacement: &str) -> String {
+		match self.find(haystack) {
+			Some(span) => {
+				let mut replaced = String::with_capacity(haystack.len() + replacement.len());
+				replaced.push_str(&haystack[..span.start]);
+				replaced.push_str(replacement);
+				replaced.push_str(&haystack[span.end..]);
+				replaced
+			},
+			None => String::from(haystack)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::format;
+	use alloc::string::String;
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use crate::regexp::{Re, ReFlags};
+	use super::{Backend, BudgetExceeded, Span};
+
+	fn multiline() -> ReFlags {
+		ReFlags{multiline: true, ..ReFlags::default()}
+	}
+
+	fn find_multiline(pattern: &str, haystack: &str) -> Option<Span> {
+		Re::parse_regexp(pattern).unwrap().find_with_flags(haystack, &multiline())
+	}
+
+	fn find(pattern: &str, haystack: &str) -> Option<Span> {
+		Re::parse_regexp(pattern).unwrap().find(haystack)
+	}
+
+	#[test]
+	fn full_match() {
+		let reg = Re::parse_regexp("(a|b)*a").unwrap();
+		assert!(reg.is_match("a"));
+		assert!(reg.is_match("abba"));
+		assert!(!reg.is_match("ab"));
+		assert!(!reg.is_match(""));
+
+		let reg = Re::parse_regexp("a{2,3}b+").unwrap();
+		assert!(reg.is_match("aab"));
+		assert!(reg.is_match("aaabbb"));
+		assert!(!reg.is_match("ab"));
+		assert!(!reg.is_match("aaaab"));
+
+		assert!(Re::parse_regexp("(a*)*b").unwrap().is_match("aab"));
+	}
+
+	#[test]
+	fn leftmost_longest_search() {
+		assert_eq!(find("b+", "aabbbc"), Some(Span{start: 2, end: 5}));
+		assert_eq!(find("a*", "bbb"), Some(Span{start: 0, end: 0}));
+		assert_eq!(find("c", "ab"), None);
+		assert_eq!(find("b.", "çbé"), Some(Span{start: 2, end: 5}));
+	}
+
+	#[test]
+	fn longest_alternative() {
+		// leftmost-shortest would stop at the a
+		assert_eq!(find("a|ab", "xabx"), Some(Span{start: 1, end: 3}));
+		assert_eq!(find("ab|a", "xabx"), Some(Span{start: 1, end: 3}));
+		// the leftmost start wins over a longer match further right
+		assert_eq!(find("a|bcd", "xabcd"), Some(Span{start: 1, end: 2}));
+		assert_eq!(find("^(a|ab)", "xabx"), None);
+		assert_eq!(find("^(a|ab)", "abx"), Some(Span{start: 0, end: 2}));
+	}
+
+	#[test]
+	fn absolute_anchors() {
+		assert_eq!(find("\\Aabc\\z", "abc"), Some(Span{start: 0, end: 3}));
+		assert_eq!(find("\\Aabc\\z", "xabc"), None);
+		assert_eq!(find("\\Aabc\\z", "abcx"), None);
+		assert_eq!(find("\\Ab", "ab"), None);
+		assert_eq!(find("a\\z", "aba"), Some(Span{start: 2, end: 3}));
+	}
+
+	#[test]
+	fn multiline_anchors() {
+		assert_eq!(find("^b", "a\nb"), None);
+		assert_eq!(find_multiline("^b", "a\nb"), Some(Span{start: 2, end: 3}));
+
+		assert_eq!(find("a$", "a\nb"), None);
+		assert_eq!(find_multiline("a$", "a\nb"), Some(Span{start: 0, end: 1}));
+
+		assert_eq!(find("^a.$", "xy\nab\n"), None);
+		assert_eq!(find_multiline("^a.$", "xy\nab\n"), Some(Span{start: 3, end: 5}));
+	}
+
+	#[test]
+	fn absolute_anchors_ignore_multiline() {
+		assert_eq!(find_multiline("^abc$", "x\nabc\ny"), Some(Span{start: 2, end: 5}));
+		assert_eq!(find_multiline("\\Aabc\\z", "x\nabc\ny"), None);
+		assert_eq!(find_multiline("\\Aabc\\z", "abc"), Some(Span{start: 0, end: 3}));
+		assert!(!Re::parse_regexp("a$b").unwrap().is_match_with_flags("a\nb", &multiline()));
+	}
+
+	fn span(start: usize, end: usize) -> Option<Span> {
+		Some(Span{start, end})
+	}
+
+	#[test]
+	fn group_captures() {
+		assert_eq!(Re::parse_regexp("(a|b)*").unwrap().captures("abb"), Some(vec![span(0, 3), span(2, 3)]));
+		assert_eq!(Re::parse_regexp("x(\\d+)").unwrap().captures("ax12"), Some(vec![span(1, 4), span(2, 4)]));
+		assert_eq!(Re::parse_regexp("(a)|(b)").unwrap().captures("b"), Some(vec![span(0, 1), None, span(0, 1)]));
+		assert_eq!(Re::parse_regexp("(a)").unwrap().captures("b"), None);
+	}
+
+	#[test]
+	fn captures_iteration() {
+		let reg = Re::parse_regexp("(\\w)=(\\d)").unwrap();
+		let haystack = "a=1;b=2";
+
+		let pairs: Vec<(&str, &str)> = reg.captures_iter(haystack)
+			.map(|captures| (captures[1].unwrap().as_str(haystack), captures[2].unwrap().as_str(haystack)))
+			.collect();
+		assert_eq!(pairs, vec![("a", "1"), ("b", "2")]);
+
+		for captures in reg.captures_iter(haystack) {
+			assert_eq!(captures.len(), 3);
+		}
+	}
+
+	#[test]
+	fn empty_matches_advance() {
+		let reg = Re::parse_regexp("a*").unwrap();
+		let spans: Vec<Span> = reg.find_iter("baé").collect();
+		assert_eq!(spans, vec![Span{start: 0, end: 0}, Span{start: 1, end: 2}, Span{start: 2, end: 2}, Span{start: 4, end: 4}]);
+	}
+
+	#[test]
+	fn anchored_scanning() {
+		let token = Re::parse_regexp("\\d+|\\w+|=|\\s+").unwrap();
+		let input = "x1 = 42";
+
+		let mut tokens = Vec::new();
+		let mut pos = 0;
+		while let Some(span) = token.find_at(input, pos) {
+			tokens.push(span.as_str(input));
+			pos = span.end;
+		}
+		assert_eq!(pos, input.len());
+		assert_eq!(tokens, vec!["x1", " ", "=", " ", "42"]);
+
+		// only the given position is tried
+		assert_eq!(token.find_at("==x", 1), Some(Span{start: 1, end: 2}));
+		assert_eq!(Re::parse_regexp("x").unwrap().find_at("==x", 1), None);
+	}
+
+	#[test]
+	fn find_at_char_boundary() {
+		let reg = Re::parse_regexp(".").unwrap();
+		assert_eq!(reg.find_at("éa", 1), None);
+		assert_eq!(reg.find_at("éa", 2), Some(Span{start: 2, end: 3}));
+		assert_eq!(reg.find_at("éa", 4), None);
+	}
+
+	#[test]
+	fn nary_matching() {
+		for pattern in ["a|b|c", "ab(c|d)*e", "(a|bc)+d"] {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			let nary = reg.to_nary();
+			for input in ["a", "c", "abe", "abcdce", "abd", "abcabd", "bcad", "x"] {
+				assert_eq!(reg.is_match(input), nary.is_match(input));
+				assert_eq!(reg.find(input), nary.find(input));
+			}
+		}
+	}
+
+	#[test]
+	fn grapheme_cluster_any_char() {
+		let flags = ReFlags{grapheme_clusters: true, ..ReFlags::default()};
+		let reg = Re::parse_regexp("a.b").unwrap();
+
+		// e followed by a combining acute accent
+		assert!(!reg.is_match("ae\u{301}b"));
+		assert!(reg.is_match_with_flags("ae\u{301}b", &flags));
+
+		// family emoji joined by zero width joiners
+		let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+		assert!(Re::parse_regexp(".").unwrap().is_match_with_flags(family, &flags));
+		assert!(!Re::parse_regexp(".").unwrap().is_match(family));
+
+		assert!(Re::parse_regexp("...").unwrap().is_match_with_flags("xyz", &flags));
+		assert!(!Re::parse_regexp(".").unwrap().is_match_with_flags("\n", &flags));
+	}
+
+	#[test]
+	fn prefix_with_rest() {
+		let reg = Re::parse_regexp("\\d+").unwrap();
+		assert_eq!(reg.match_prefix("123abc"), Some((Span{start: 0, end: 3}, "abc")));
+		assert_eq!(reg.match_prefix("42"), Some((Span{start: 0, end: 2}, "")));
+		assert_eq!(reg.match_prefix("a1"), None);
+
+		// the longest prefix, whatever the order of the alternatives
+		let reg = Re::parse_regexp("a|ab").unwrap();
+		assert_eq!(reg.match_prefix("abé"), Some((Span{start: 0, end: 2}, "é")));
+		assert_eq!(Re::parse_regexp("x*").unwrap().match_prefix("yz"), Some((Span{start: 0, end: 0}, "yz")));
+	}
+
+	#[test]
+	fn length_precheck() {
+		let reg = Re::parse_regexp("a{3,5}").unwrap();
+		assert!(!reg.is_match(&"a".repeat(100)));
+		assert!(!reg.is_match("aa"));
+		assert!(reg.is_match("aaa"));
+		assert!(reg.is_match("aaaaa"));
+		assert!(!reg.is_match("aaaaaa"));
+
+		// the bounds count chars, not bytes
+		let reg = Re::parse_regexp(".{2,3}").unwrap();
+		assert!(reg.is_match("éé"));
+		assert!(reg.is_match("😀😀😀"));
+		assert!(!reg.is_match("😀"));
+		assert!(!reg.is_match("éééé"));
+
+		// a grapheme cluster spans several chars
+		let flags = ReFlags{grapheme_clusters: true, ..ReFlags::default()};
+		assert!(Re::parse_regexp(".").unwrap().is_match_with_flags("e\u{301}", &flags));
+	}
+
+	#[test]
+	fn large_repeat() {
+		// far above max_nfa_size once expanded, so the repetitions are counted
+		let reg = Re::parse_regexp("a{0,100000}b").unwrap();
+		assert!(reg.is_match(&("a".repeat(200) + "b")));
+		assert!(reg.is_match("b"));
+		assert!(!reg.is_match(&"a".repeat(200)));
+		assert!(reg.find("xxaab") == Some(Span{start: 2, end: 5}));
+
+		let reg = Re::parse_regexp("(ab){3,100000}").unwrap();
+		assert!(reg.is_match(&"ab".repeat(100)));
+		assert!(!reg.is_match("abab"));
+	}
+
+	#[test]
+	fn nfa_dispatch() {
+		let backtracking = ReFlags{nfa_threshold: usize::MAX, ..ReFlags::default()};
+		let nfa = ReFlags{nfa_threshold: 0, ..ReFlags::default()};
+		let patterns = ["(ab)+", "a|ab", "^(a|b)*c$", "x{1,3}y?", "[^a-c]\\d", "(a*)*", "\\Aa.\\z", "((a)|b)+"];
+		let inputs = ["", "a", "ab", "abab", "aabc", "bac", "xxy", "z7", "a\n", "aé", "aaaa"];
+		for pattern in patterns {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			for input in inputs {
+				assert_eq!(reg.is_match_with_flags(input, &backtracking), reg.is_match_with_flags(input, &nfa), "{pattern} {input}");
+			}
+		}
+
+		// an atomic group stays on the backtracking path
+		let reg = Re::parse_regexp("(?>a*)a").unwrap();
+		assert!(!reg.is_match_with_flags("aa", &nfa));
+		assert!(!reg.is_match(&"a".repeat(100)));
+	}
+
+	#[test]
+	fn program_dispatch() {
+		// backtracking would try every way to split the a's
+		let reg = Re::parse_regexp("((a|a)*)b").unwrap();
+		assert!(reg.has_exponential_risk());
+		let haystack = "a".repeat(60);
+		assert_eq!(reg.find(&haystack), None);
+		assert_eq!(reg.captures(&haystack), None);
+		assert_eq!(reg.captures_iter(&haystack).count(), 0);
+
+		let haystack = format!("{}bxab", "a".repeat(60));
+		assert_eq!(reg.find(&haystack), Some(Span{start: 0, end: 61}));
+		let spans: Vec<Span> = reg.captures_iter(&haystack).map(|captures| captures[1].unwrap()).collect();
+		assert_eq!(spans, vec![Span{start: 0, end: 60}, Span{start: 62, end: 63}]);
+	}
+
+	#[test]
+	fn compiled_reuse() {
+		let inputs = ["", "2024-01-05", "x2024-1-5y", "ab", "aab", "abab", "a\nb", "é1"];
+		for pattern in ["(\\d+)-(\\d+)-(\\d+)", "(a|ab)(b*)", "^a.b$", "(?>a+)b", "\\w*", "(a{0,4000})(b){0,3000}"] {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			let compiled = reg.compiled();
+			for input in inputs {
+				assert_eq!(compiled.is_match(input), reg.is_match(input), "{pattern} {input}");
+				assert_eq!(compiled.find(input), reg.find(input), "{pattern} {input}");
+				assert_eq!(compiled.captures(input), reg.captures(input), "{pattern} {input}");
+			}
+		}
+
+		// past max_nfa_size no automaton is built
+		let compiled = Re::parse_regexp("a{0,400000}").unwrap().compiled();
+		assert!(compiled.automata.is_none());
+		assert!(compiled.is_match("aaa"));
+		assert!(Re::parse_regexp("a{0,40}").unwrap().compiled().automata.is_some());
+	}
+
+	#[test]
+	fn dotall() {
+		let dotall = ReFlags{dotall: true, ..ReFlags::default()};
+		let dot = Re::parse_regexp("a.b").unwrap();
+		let class = Re::parse_regexp("a[^\\n]b").unwrap();
+		for input in ["axb", "a\nb", "a\rb", "ab", "aéb"] {
+			assert_eq!(dot.is_match(input), class.is_match(input), "{input}");
+			assert_eq!(dot.find(input), class.find(input), "{input}");
+		}
+
+		// only . changes under the flag
+		assert!(dot.is_match_with_flags("a\nb", &dotall));
+		assert!(!class.is_match_with_flags("a\nb", &dotall));
+		assert_eq!(dot.find_with_flags("xa\nb", &dotall), Some(Span{start: 1, end: 4}));
+		assert!(class.find_with_flags("xa\nb", &dotall).is_none());
+		assert!(Re::parse_regexp(".*").unwrap().is_match_with_flags(&"\n".repeat(100), &dotall));
+	}
+
+	#[test]
+	fn char_offsets() {
+		let input = "héllo wörld";
+		let span = Re::parse_regexp("w.r").unwrap().find(input).unwrap();
+		assert_eq!(span, Span{start: 7, end: 11});
+		assert_eq!(span.char_range(input), 6..9);
+
+		let span = Re::parse_regexp("\\u{e9}+").unwrap().find("aééb").unwrap();
+		assert_eq!((span.start..span.end, span.char_range("aééb")), (1..5, 1..3));
+		assert_eq!(Span{start: 5, end: 5}.char_range("ab€"), 3..3);
+	}
+
+	#[test]
+	fn spans_on_char_boundaries() {
+		let input = "é.ü€x😀ab\u{301}c";
+		let backends = [Backend::Backtracking, Backend::Nfa, Backend::Dfa, Backend::Pike];
+		for pattern in [".", "..", "[^a-z]+", "x.", ".*", "(.)(.?)", "\\W", "b.c"] {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			for span in reg.find_iter(input).chain(reg.captures_iter(input).flatten().flatten()) {
+				assert!(input.is_char_boundary(span.start) && input.is_char_boundary(span.end), "{pattern}");
+				assert_eq!(span.get(input), Some(span.as_str(input)));
+			}
+			for backend in backends {
+				let span = reg.compile(backend).find(input).unwrap();
+				assert!(span.get(input).is_some(), "{pattern}");
+			}
+			assert_eq!(reg.match_indices(input).iter().map(|(_, text)| text.len()).sum::<usize>(),
+				reg.find_iter(input).map(|span| span.end - span.start).sum::<usize>());
+		}
+
+		// a span from another input can split a char
+		assert_eq!(Span{start: 0, end: 1}.get("é"), None);
+		assert_eq!(Span{start: 2, end: 4}.get("é"), None);
+	}
+
+	#[test]
+	fn custom_char_equivalence() {
+		let fold_accent = |c: char, d: char| c == d || matches!((c, d), ('a', 'á') | ('á', 'a'));
+		let reg = Re::parse_regexp("m(a|e)nana").unwrap();
+		assert!(reg.is_match_with("manána", fold_accent));
+		assert!(reg.is_match_with("mánana", fold_accent));
+		assert!(!reg.is_match("manána"));
+		assert!(!reg.is_match_with("manéna", fold_accent));
+
+		// a literal pattern does not take the substring shortcut
+		assert!(Re::parse_regexp("abc").unwrap().is_match_with("ábc", fold_accent));
+		assert!(Re::parse_regexp("[a-c]").unwrap().is_match_with("b", |_, _| false));
+	}
+
+	#[test]
+	fn ambiguous_matches() {
+		let splits = |pattern: &str, input: &str| Re::parse_regexp(pattern).unwrap().match_all_splits(input);
+
+		assert_eq!(splits("(a|a)", "a"), 2);
+		assert_eq!(splits("a|b", "a"), 1);
+		assert_eq!(splits("a*a*", "aa"), 3);
+		assert_eq!(splits("(a|ab)(c|bc)", "abc"), 2);
+		assert_eq!(splits("(a*)*", "aa"), 2);
+		assert_eq!(splits("(a{0,1}){2,2}", "a"), 2);
+		assert_eq!(splits("a*", ""), 1);
+		assert_eq!(splits("ab", "abc"), 0);
+	}
+
+	#[test]
+	fn atomic_groups() {
+		let reg = Re::parse_regexp("(?>a*)a").unwrap();
+		assert!(!reg.is_match("aaa"));
+		assert!(Re::parse_regexp("(a*)a").unwrap().is_match("aaa"));
+		assert!(Re::parse_regexp("(?>a*)b").unwrap().is_match("aab"));
+
+		// the first alternative that matches is kept
+		assert!(!Re::parse_regexp("(?>a|ab)c").unwrap().is_match("abc"));
+		assert!(Re::parse_regexp("(?>ab|a)c").unwrap().is_match("abc"));
+		assert_eq!(find("(?>a|ab)", "ab"), Some(Span{start: 0, end: 1}));
+
+		// captures of a failed attempt do not leak into the match
+		let reg = Re::parse_regexp("(?>(a))x|ay").unwrap();
+		assert_eq!(reg.captures("ay"), Some(vec![Some(Span{start: 0, end: 2}), None]));
+		assert_eq!(reg.captures("ax"), Some(vec![Some(Span{start: 0, end: 2}), Some(Span{start: 0, end: 1})]));
+	}
+
+	#[test]
+	fn named_captures() {
+		let reg = Re::parse_regexp("(?P<year>\\d\\d\\d\\d)-(?P<month>\\d\\d)(-(?P<day>\\d\\d))?").unwrap();
+		let fields = reg.captures_map("2024-03").unwrap();
+		assert_eq!(fields.len(), 2);
+		assert_eq!(fields["year"].as_str("2024-03"), "2024");
+		assert_eq!(fields["month"], Span{start: 5, end: 7});
+
+		assert_eq!(reg.captures_map("on 2024-03-14").unwrap()["day"], Span{start: 11, end: 13});
+		assert!(reg.captures_map("2024").is_none());
+		assert!(Re::parse_regexp("(a)").unwrap().captures_map("a").unwrap().is_empty());
+	}
+
+	#[test]
+	fn whole_input_captures() {
+		let reg = Re::parse_regexp("(\\d\\d\\d\\d)-(\\d\\d)-(\\d\\d)").unwrap();
+		let input = "2024-01-05";
+		let captures = reg.fullmatch_captures(input).unwrap();
+		let parts: Vec<&str> = captures.iter().map(|span| span.unwrap().as_str(input)).collect();
+		assert_eq!(parts, vec!["2024-01-05", "2024", "01", "05"]);
+
+		assert_eq!(reg.fullmatch_captures("2024-01-05x"), None);
+		assert_eq!(reg.fullmatch_captures("x2024-01-05"), None);
+		assert_eq!(reg.fullmatch_captures("2024-01"), None);
+
+		let reg = Re::parse_regexp("(a|ab)(c|bcd)?").unwrap();
+		assert_eq!(reg.fullmatch_captures("abcd"), Some(vec![Some(Span{start: 0, end: 4}), Some(Span{start: 0, end: 1}), Some(Span{start: 1, end: 4})]));
+		assert_eq!(reg.fullmatch_captures("a"), Some(vec![Some(Span{start: 0, end: 1}), Some(Span{start: 0, end: 1}), None]));
+	}
+
+	#[test]
+	fn match_indices() {
+		let reg = Re::parse_regexp("\\d+").unwrap();
+		assert_eq!(reg.match_indices("a12b3"), vec![(1, String::from("12")), (4, String::from("3"))]);
+		assert_eq!(reg.match_indices("ab"), vec![]);
+
+		let reg = Re::parse_regexp("b?").unwrap();
+		assert_eq!(reg.match_indices("ab"), vec![(0, String::new()), (1, String::from("b")), (2, String::new())]);
+	}
+
+	#[test]
+	fn inclusive_split() {
+		let reg = Re::parse_regexp("\\d").unwrap();
+		assert_eq!(reg.split_inclusive("a1b2"), vec![String::from("a1"), String::from("b2")]);
+		assert_eq!(reg.split_inclusive("a1bc"), vec![String::from("a1"), String::from("bc")]);
+		assert_eq!(reg.split_inclusive("12"), vec![String::from("1"), String::from("2")]);
+		assert!(reg.split_inclusive("").is_empty());
+
+		let reg = Re::parse_regexp("\n|$").unwrap();
+		assert_eq!(reg.split_inclusive("x\ny"), vec![String::from("x\n"), String::from("y")]);
+	}
+
+	#[test]
+	fn literal_fast_path() {
+		let reg = Re::parse_regexp("hello").unwrap();
+		assert_eq!(reg.find("say hello, hello"), Some(Span{start: 4, end: 9}));
+		assert_eq!(reg.find("help"), None);
+		assert!(reg.is_match("hello"));
+		assert!(!reg.is_match("hello!"));
+
+		let spans: Vec<Span> = Re::parse_regexp("ab").unwrap().find_iter("abxab").collect();
+		assert_eq!(spans, vec![Span{start: 0, end: 2}, Span{start: 3, end: 5}]);
+		assert_eq!(Re::and(Re::char('é'), Re::char('!')).find("aé!"), Some(Span{start: 1, end: 4}));
+	}
+
+	#[test]
+	fn first_replacement() {
+		let reg = Re::parse_regexp("a+").unwrap();
+		assert_eq!(reg.replace_first("aa bb aa", "x"), "x bb aa");
+		assert_eq!(reg.replace_first("bb", "x"), "bb");
+		assert_eq!(Re::parse_regexp("b*").unwrap().replace_first("ab", "-"), "-ab");
+	}
+
+	#[test]
+	fn char_stream_matching() {
+		let reg = Re::parse_regexp("(ab)+").unwrap();
+		assert!(reg.is_match_chars("abab".chars()));
+		assert!(!reg.is_match_chars("aba".chars()));
+		assert!(!reg.is_match_chars("".chars()));
+
+		let reg = Re::parse_regexp("^a.$").unwrap();
+		assert!(reg.is_match_chars(['a', 'é'].into_iter()));
+		assert!(!reg.is_match_chars("a\n".chars()));
+
+		// the atomic group gives back nothing, as with is_match
+		let reg = Re::parse_regexp("(?>a*)a").unwrap();
+		assert!(!reg.is_match_chars("aa".chars()));
+		assert!(Re::parse_regexp("(?>a*)b").unwrap().is_match_chars("aab".chars()));
+	}
+
+	#[test]
+	fn prefix_matching() {
+		let reg = Re::parse_regexp("abc").unwrap();
+		assert_eq!(reg.longest_prefix_match("abx"), 2);
+		assert_eq!(reg.longest_prefix_match("abc"), 3);
+		assert_eq!(reg.longest_prefix_match("ab"), 2);
+		assert_eq!(reg.longest_prefix_match("xbc"), 0);
+		assert_eq!(reg.longest_prefix_match("abcd"), 3);
+
+		let reg = Re::parse_regexp("\\d+(x\\d+){0,1}").unwrap();
+		assert_eq!(reg.longest_prefix_match("12x3y"), 4);
+		assert_eq!(reg.longest_prefix_match("é"), 0);
+	}
+
+	#[test]
+	fn valid_prefixes() {
+		let reg = Re::parse_regexp("\\d{4,4}-\\d{2,2}").unwrap();
+		assert!(reg.is_valid_prefix("20"));
+		assert!(reg.is_valid_prefix(""));
+		assert!(reg.is_valid_prefix("2024-01"));
+		assert!(!reg.is_valid_prefix("2x"));
+		assert!(!reg.is_valid_prefix("2024-012"));
+
+		assert!(!Re::Alt(vec![]).is_valid_prefix(""));
+		assert!(Re::parse_regexp("a|bc").unwrap().is_valid_prefix("b"));
+
+		// the automaton matches an atomic group like a plain one, so aa is
+		// a prefix even though is_match rejects every input
+		let reg = Re::parse_regexp("(?>a*)a").unwrap();
+		assert!(reg.is_valid_prefix("aa"));
+		assert_eq!(reg.longest_prefix_match("aab"), 2);
+		assert!(!reg.is_match("aa"));
+	}
+
+	#[test]
+	fn step_budget() {
+		let reg = Re::parse_regexp("(a*)*b").unwrap();
+		let input = "a".repeat(40);
+		assert_eq!(reg.is_match_with_budget(&input, 100_000), Err(BudgetExceeded));
+		assert_eq!(reg.is_match_with_budget("aab", 100_000), Ok(true));
+
+		let reg = Re::parse_regexp("ab*").unwrap();
+		assert_eq!(reg.is_match_with_budget("abbb", 100), Ok(true));
+		assert_eq!(reg.is_match_with_budget("abbc", 100), Ok(false));
+		assert_eq!(reg.is_match_with_budget("abbb", 2), Err(BudgetExceeded));
+	}
+
+	#[test]
+	fn backends_agree() {
+		let patterns = [
+			"(a|b)*a", "a{2,3}b+", "^ab|c$", "\\Aa*\\z", "(a*)*b", "x\\d+y{0,1}", ".b|\\w\\W",
+			"(ab|a)(bc|c)", "$", "^", "a{0,0}b", "^$", "(a|^)b", "[a-c]+", "[^a ]b", "ab?c", "(a|b)?b"
+		];
+		let inputs = ["", "a", "b", "ab", "aab", "abba", "aaabbb", "c", "xab", "abc", "x12y", "\nb", "é,", "q b"];
+
+		for pattern in patterns {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			let matchers = [Backend::Backtracking, Backend::Nfa, Backend::Dfa, Backend::Pike].map(|backend| reg.compile(backend));
+
+			for input in inputs {
+				let expected = (reg.is_match(input), reg.find(input));
+				for matcher in &matchers {
+					assert_eq!((matcher.is_match(input), matcher.find(input)), expected, "{pattern} on {input:?}");
+				}
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,100 @@
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use super::Re;
+
+impl Re {
+	// The tree as a Mermaid flowchart, a node per line followed by an edge
+	// to each child. The leaves are labelled with their pattern and the other
+	// nodes with their variant.
+	pub fn to_mermaid(&self) -> String {
+		let mut chart = String::from("graph TD\n");
+		let mut count = 0;
+		write_mermaid(self, &mut chart, &mut count);
+		chart
+	}
+}
+
+// Write the node and its children, numbering them from count in pre-order.
+// Returns the id of the node.
+fn write_mermaid(reg: &Re, chart: &mut String, count: &mut usize) -> usize {
+	let id = *count;
+	*count += 1;
+
+	let label = match reg {
+		Re::Empty => String::from("Empty"),
+		Re::Char(_) | Re::Class(_) | Re::AnyChar | Re::Start | Re::End | Re::TextStart | Re::TextEnd => reg.to_regex_crate_pattern(),
+		Re::Or(_, _) => String::from("Or"),
+		Re::And(_, _) => String::from("And"),
+		Re::Kleen(_) => String::from("Kleen"),
+		Re::OneOrMore(_) => String::from("OneOrMore"),
+		Re::Optional(_) => String::from("Optional"),
+		Re::Atomic(_) => String::from("Atomic"),
+		Re::Repeat(_, a, b) => format!("Repeat {{{a},{b}}}"),
+		Re::Group(_, index) => format!("Group {index}"),
+		Re::NamedGroup(_, index, name) => format!("Group {index} {name}"),
+		Re::Concat(_) => String::from("Concat"),
+		Re::Alt(_) => String::from("Alt")
+	};
+	chart.push_str(&format!("\tn{id}[\"{}\"]\n", escape_label(&label)));
+
+	let mut write_child = |child: &Rc<Re>, chart: &mut String| {
+		let child_id = write_mermaid(child, chart, count);
+		chart.push_str(&format!("\tn{id} --> n{child_id}\n"));
+	};
+	match reg {
+		Re::Or(c1, c2) | Re::And(c1, c2) => {
+			write_child(c1, chart);
+			write_child(c2, chart);
+		},
+		Re::Kleen(c) | Re::OneOrMore(c) | Re::Optional(c) | Re::Atomic(c) | Re::Repeat(c, _, _) | Re::Group(c, _) | Re::NamedGroup(c, _, _) => write_child(c, chart),
+		Re::Concat(children) | Re::Alt(children) => for child in children {
+			write_child(child, chart);
+		},
+		_ => ()
+	}
+	id
+}
+
+// Label with the chars Mermaid would read as syntax, and the control chars,
+// written as entity codes
+fn escape_label(label: &str) -> String {
+	let mut escaped = String::with_capacity(label.len());
+	for c in label.chars() {
+		match c {
+			'"' => escaped.push_str("#quot;"),
+			'#' => escaped.push_str("#35;"),
+			'<' => escaped.push_str("#lt;"),
+			'>' => escaped.push_str("#gt;"),
+			'&' => escaped.push_str("#amp;"),
+			c if c.is_control() => escaped.push_str(&format!("#{};", c as u32)),
+			c => escaped.push(c)
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::regexp::Re;
+
+	#[test]
+	fn mermaid_chart() {
+		let chart = Re::parse_regexp("(a|b)*").unwrap().to_mermaid();
+		assert!(chart.starts_with("graph TD\n"));
+		// Kleen, Group, Or, a and b
+		assert_eq!(chart.matches("[\"").count(), 5);
+		assert_eq!(chart.matches(" --> ").count(), 4);
+		assert!(chart.contains("\tn0[\"Kleen\"]\n\tn1[\"Group 1\"]\n"));
+		assert!(chart.contains("\tn2 --> n4\n"));
+	}
+
+	#[test]
+	fn mermaid_escaping() {
+		let chart = Re::parse_regexp("\"|#|<|\\t").unwrap().to_mermaid();
+		assert!(chart.contains("[\"#quot;\"]"));
+		assert!(chart.contains("[\"\\#35;\"]"));
+		assert!(chart.contains("[\"#lt;\"]"));
+		assert!(chart.contains("[\"#9;\"]"));
+	}
+}
@@ -0,0 +1,56 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use super::{Re, Span};
+
+impl Re {
+	// Alternation of all the patterns, in order, matching nothing when there
+	// are none
+	pub fn union(patterns: &[Re]) -> Re {
+		Re::Alt(patterns.iter().map(|pattern| Rc::from(pattern.clone())).collect())
+	}
+}
+
+// Set of patterns searched at once, as the token rules of a scanner
+pub struct MultiMatcher {
+	patterns: Vec<Re>,
+	union: Re
+}
+
+impl MultiMatcher {
+	pub fn new(patterns: Vec<Re>) -> Self {
+		let union = Re::union(&patterns);
+		MultiMatcher{patterns, union}
+	}
+
+	// Leftmost-longest match of any of the patterns, with the index of the
+	// pattern that matched. A tie between patterns goes to the first one.
+	pub fn find_which(&self, input: &str) -> Option<(usize, Span)> {
+		let span = self.union.find(input)?;
+		let index = self.patterns.iter()
+			.position(|pattern| pattern.find_at(input, span.start) == Some(span))
+			.unwrap();
+		Some((index, span))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec::Vec;
+	use crate::regexp::{Backend, Re, Span};
+	use super::MultiMatcher;
+
+	#[test]
+	fn winning_pattern() {
+		let tokens = MultiMatcher::new(["if", "\\w+", "\\d+"].iter().map(|pattern| Re::parse_regexp(pattern).unwrap()).collect());
+
+		assert_eq!(tokens.find_which("  if x"), Some((0, Span{start: 2, end: 4})));
+		assert_eq!(tokens.find_which("iffy"), Some((1, Span{start: 0, end: 4})));
+		assert_eq!(tokens.find_which("-42"), Some((1, Span{start: 1, end: 3})));
+		assert_eq!(tokens.find_which("+-"), None);
+
+		assert_eq!(MultiMatcher::new(Vec::new()).find_which("abc"), None);
+		for backend in [Backend::Backtracking, Backend::Nfa, Backend::Dfa, Backend::Pike] {
+			assert!(!Re::union(&[]).compile(backend).is_match(""));
+		}
+	}
+}
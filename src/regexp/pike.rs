@@ -0,0 +1,297 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{Captures, CharClass, Re, Span};
+
+// Instruction of a compiled program, the operands being program counters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inst {
+	Char(char),
+	Any,
+	Class(CharClass),
+	// ^ and \A, $ and \z, matched with the default flags
+	AssertStart,
+	AssertEnd,
+	// fork, the first thread having priority over the second one
+	Split(usize, usize),
+	Jmp(usize),
+	// record the position in a capture slot, 2k and 2k+1 being the start
+	// and end of group k
+	Save(usize),
+	Match
+}
+
+// Regexp compiled for Pike's VM, which runs every thread of the program in
+// lockstep over the input so a match takes linear time even with captures
+pub struct Program {
+	insts: Vec<Inst>,
+	slots: usize
+}
+
+impl Re {
+	pub fn compile_program(&self) -> Program {
+		let mut program = Program{insts: Vec::new(), slots: 2 * (self.group_count() + 1)};
+		program.emit(self);
+		program.insts.push(Inst::Match);
+		program
+	}
+}
+
+impl Program {
+	pub fn instructions(&self) -> &[Inst] {
+		&self.insts
+	}
+
+	fn push(&mut self, inst: Inst) -> usize {
+		self.insts.push(inst);
+		self.insts.len() - 1
+	}
+
+	fn emit(&mut self, reg: &Re) {
+		match reg {
+			Re::Empty => (),
+			Re::Char(c) => { self.push(Inst::Char(*c)); },
+			Re::AnyChar => { self.push(Inst::Any); },
+			Re::Class(class) => { self.push(Inst::Class(class.clone())); },
+			Re::Start | Re::TextStart => { self.push(Inst::AssertStart); },
+			Re::End | Re::TextEnd => { self.push(Inst::AssertEnd); },
+			// a thread cannot drop the other positions of the group, so it
+			// matches like a plain one
+			Re::Atomic(c) => self.emit(c),
+			Re::Group(c, index) | Re::NamedGroup(c, index, _) => {
+				self.push(Inst::Save(2 * index));
+				self.emit(c);
+				self.push(Inst::Save(2 * index + 1));
+			},
+			Re::And(c1, c2) => {
+				self.emit(c1);
+				self.emit(c2);
+			},
+			Re::Concat(children) => children.iter().for_each(|child| self.emit(child)),
+			Re::Or(c1, c2) => self.emit_alternation(&[c1.as_ref(), c2.as_ref()]),
+			Re::Alt(children) => self.emit_alternation(&children.iter().map(|child| child.as_ref()).collect::<Vec<_>>()),
+			Re::Kleen(c) => {
+				let split = self.push(Inst::Split(0, 0));
+				self.emit(c);
+				self.push(Inst::Jmp(split));
+				self.insts[split] = Inst::Split(split + 1, self.insts.len());
+			},
+			Re::OneOrMore(c) => {
+				let body = self.insts.len();
+				self.emit(c);
+				self.push(Inst::Split(body, self.insts.len() + 1));
+			},
+			Re::Optional(c) => {
+				let split = self.push(Inst::Split(0, 0));
+				self.emit(c);
+				self.insts[split] = Inst::Split(split + 1, self.insts.len());
+			},
+			Re::Repeat(c, a, b) => {
+				for _ in 0..*a {
+					self.emit(c);
+				}
+				// each optional copy can skip all the following ones
+				let mut splits = Vec::new();
+				for _ in *a..*b {
+					splits.push(self.push(Inst::Split(0, 0)));
+					self.emit(c);
+				}
+				let end = self.insts.len();
+				for split in splits {
+					self.insts[split] = Inst::Split(split + 1, end);
+				}
+			}
+		}
+	}
+
+	fn emit_alternation(&mut self, alternatives: &[&Re]) {
+		let Some((last, rest)) = alternatives.split_last() else {
+			// an empty class, which no thread gets through
+			self.push(Inst::Class(CharClass::new(Vec::new(), false)));
+			return;
+		};
+
+		let mut jumps = Vec::new();
+		for alternative in rest {
+			let split = self.push(Inst::Split(0, 0));
+			self.emit(alternative);
+			jumps.push(self.push(Inst::Jmp(0)));
+			self.insts[split] = Inst::Split(split + 1, self.insts.len());
+		}
+		self.emit(last);
+
+		let end = self.insts.len();
+		for jump in jumps {
+			self.insts[jump] = Inst::Jmp(end);
+		}
+	}
+
+	// Add the thread at pc to the list, following the instructions that do
+	// not consume a char. A pc already in the list is reached by a thread
+	// of higher priority and is skipped.
+	fn add_thread(&self, list: &mut ThreadList, pc: usize, input: &str, pos: usize, slots: &mut Vec<Option<usize>>) {
+		if list.seen[pc] {
+			return;
+		}
+		list.seen[pc] = true;
+
+		match &self.insts[pc] {
+			Inst::Jmp(next) => self.add_thread(list, *next, input, pos, slots),
+			Inst::Split(first, second) => {
+				self.add_thread(list, *first, input, pos, slots);
+				self.add_thread(list, *second, input, pos, slots);
+			},
+			Inst::Save(slot) => {
+				let previous = slots[*slot];
+				slots[*slot] = Some(pos);
+				self.add_thread(list, pc + 1, input, pos, slots);
+				slots[*slot] = previous;
+			},
+			Inst::AssertStart => if pos == 0 {
+				self.add_thread(list, pc + 1, input, pos, slots);
+			},
+			Inst::AssertEnd => if pos == input.len() {
+				self.add_thread(list, pc + 1, input, pos, slots);
+			},
+			_ => list.threads.push((pc, slots.clone()))
+		}
+	}
+
+	// Longest match starting at start, with the captures of the thread of
+	// highest priority reaching that end. An unanchored search also starts a
+	// thread of lowest priority at each position after start until a match
+	// is found, so the list stays ordered by start and the first start to
+	// match wins in a single pass.
+	fn search(&self, input: &str, start: usize, unanchored: bool) -> Option<Captures> {
+		let mut best: Option<Vec<Option<usize>>> = None;
+		let mut current = ThreadList::new(self.insts.len());
+
+		let mut pos = start;
+		loop {
+			if best.is_none() && (unanchored || pos == start) {
+				let mut slots = vec![None; self.slots];
+				slots[0] = Some(pos);
+				self.add_thread(&mut current, 0, input, pos, &mut slots);
+			}
+
+			let c = input[pos..].chars().next();
+			let after = pos + c.map_or(0, |c| c.len_utf8());
+			let mut next = ThreadList::new(self.insts.len());
+			let mut matched = false;
+
+			for (pc, slots) in &mut current.threads {
+				// a thread starting after the match found cannot beat it
+				if best.as_ref().is_some_and(|best| slots[0] > best[0]) {
+					continue;
+				}
+				let consumed = match (&self.insts[*pc], c) {
+					(Inst::Match, _) => {
+						// later threads have a lower priority for this end
+						if !matched {
+							matched = true;
+							slots[1] = Some(pos);
+							best = Some(slots.clone());
+						}
+						false
+					},
+					(Inst::Char(d), Some(c)) => *d == c,
+					(Inst::Any, Some(c)) => c != '\n',
+					(Inst::Class(class), Some(c)) => class.contains(c),
+					_ => false
+				};
+				if consumed {
+					self.add_thread(&mut next, *pc + 1, input, after, slots);
+				}
+			}
+
+			if c.is_none() || (next.threads.is_empty() && (best.is_some() || !unanchored)) {
+				break;
+			}
+			current = next;
+			pos = after;
+		}
+
+		best.map(|slots| {
+			slots.chunks(2)
+				.map(|bounds| match bounds {
+					[Some(start), Some(end)] => Some(Span{start: *start, end: *end}),
+					_ => None
+				})
+				.collect()
+		})
+	}
+
+	fn longest_at(&self, input: &str, start: usize) -> Option<Captures> {
+		self.search(input, start, false)
+	}
+
+	// Group spans of the leftmost-longest match, like Re::captures
+	pub fn run(&self, input: &str) -> Option<Captures> {
+		self.search(input, 0, true)
+	}
+
+	// Same as run for a match starting at or after from
+	pub(super) fn run_from(&self, input: &str, from: usize) -> Option<Captures> {
+		self.search(input, from, true)
+	}
+
+	pub fn is_match(&self, input: &str) -> bool {
+		self.longest_at(input, 0).is_some_and(|captures| captures[0].unwrap().end == input.len())
+	}
+
+	pub fn find(&self, input: &str) -> Option<Span> {
+		self.run(input).and_then(|captures| captures[0])
+	}
+}
+
+// Threads of one step of the VM in priority order, with their capture slots
+struct ThreadList {
+	threads: Vec<(usize, Vec<Option<usize>>)>,
+	seen: Vec<bool>
+}
+
+impl ThreadList {
+	fn new(size: usize) -> Self {
+		ThreadList{threads: Vec::new(), seen: vec![false; size]}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::regexp::Re;
+	use super::Inst;
+
+	#[test]
+	fn program_compilation() {
+		let program = Re::parse_regexp("(a|b)*").unwrap().compile_program();
+		assert_eq!(program.instructions(), [
+			Inst::Split(1, 8),
+			Inst::Save(2),
+			Inst::Split(3, 5),
+			Inst::Char('a'),
+			Inst::Jmp(6),
+			Inst::Char('b'),
+			Inst::Save(3),
+			Inst::Jmp(0),
+			Inst::Match
+		]);
+	}
+
+	#[test]
+	fn captures_like_backtracking() {
+		let patterns = [
+			"(a|b)*", "x(\\d+)", "(a)|(b)", "(a|ab)(c|bcd)(d*)", "((a)|b)+", "(a*)*b", "(a*)+",
+			"(a{0,1})(a{1,2})", "^(?P<key>\\w+)=(\\w*)$", "(a|^)b", ".(b|c){2,3}", "([a-c]+)(c*)",
+			"a(bc)d|(b)", "(b)c|a(b)cd", "c$|(a)"
+		];
+		let inputs = ["", "a", "b", "abb", "ax12", "abcd", "abcdd", "aab", "aaa", "key=val", "k=", "xbcb", "abccc", "xabcdx", "xabcx"];
+
+		for pattern in patterns {
+			let reg = Re::parse_regexp(pattern).unwrap();
+			let program = reg.compile_program();
+			for input in inputs {
+				assert_eq!(program.run(input), reg.captures(input), "{pattern} on {input:?}");
+				assert_eq!(program.is_match(input), reg.is_match(input), "{pattern} on {input:?}");
+			}
+		}
+	}
+}
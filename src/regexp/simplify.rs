@@ -0,0 +1,487 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use super::{CharClass, Re};
+use super::transform::chain_operands;
+
+// Passes of simplify after the first one, as a guard in case two rules
+// undo each other
+const MAX_SIMPLIFY_PASSES: usize = 16;
+
+impl Re {
+	// Equivalent regexp with redundant constructs rewritten, children first.
+	// Capturing groups are never changed, so the captures stay the same. A
+	// rewrite can make another one possible, like the alternatives of a
+	// chain of Or regrouped by dedup_alternatives becoming a class, so the
+	// passes are repeated until nothing changes.
+	pub fn simplify(&self) -> Re {
+		let mut reg = self.simplify_pass();
+		for _ in 0..MAX_SIMPLIFY_PASSES {
+			let next = reg.simplify_pass();
+			if next == reg {
+				break;
+			}
+			reg = next;
+		}
+		reg
+	}
+
+	fn simplify_pass(&self) -> Re {
+		let simplify = |c: &Rc<Re>| Rc::from(c.simplify_pass());
+		let reg = match self {
+			Re::Or(c1, c2) => Re::Or(simplify(c1), simplify(c2)),
+			Re::And(c1, c2) => Re::And(simplify(c1), simplify(c2)),
+			Re::Kleen(c) => Re::Kleen(simplify(c)),
+			Re::OneOrMore(c) => Re::OneOrMore(simplify(c)),
+			Re::Optional(c) => Re::Optional(simplify(c)),
+			Re::Repeat(c, a, b) => Re::Repeat(simplify(c), *a, *b),
+			Re::Group(c, index) => Re::Group(simplify(c), *index),
+			Re::NamedGroup(c, index, name) => Re::NamedGroup(simplify(c), *index, name.clone()),
+			Re::Atomic(c) => Re::Atomic(simplify(c)),
+			Re::Concat(children) => Re::Concat(children.iter().map(simplify).collect()),
+			Re::Alt(children) => Re::Alt(children.iter().map(simplify).collect()),
+			_ => self.clone()
+		};
+		reg.simplify_node()
+	}
+
+	// Drop the alternatives of the top-level alternation equal to an earlier
+	// one, a|b|a becoming a|b
+	pub fn dedup_alternatives(&self) -> Re {
+		match self {
+			Re::Or(_, _) => {
+				let mut alternatives = Vec::new();
+				chain_operands(&Rc::from(self.clone()), true, &mut alternatives);
+				Re::or_chain(&dedup(alternatives))
+			},
+			Re::Alt(children) => match dedup(children.clone()).as_slice() {
+				[single] => single.as_ref().clone(),
+				unique => Re::Alt(unique.to_vec())
+			},
+			_ => self.clone()
+		}
+	}
+
+	// Alternatives matching nothing removed, the alternation matching
+	// nothing only when all of them do
+	fn drop_never_alternatives(self) -> Re {
+		match &self {
+			Re::Or(_, _) => {
+				let mut alternatives = Vec::new();
+				chain_operands(&Rc::from(self.clone()), true, &mut alternatives);
+				alternatives.retain(|alternative| !is_never(alternative));
+				if alternatives.is_empty() { Re::never() } else { Re::or_chain(&alternatives) }
+			},
+			Re::Alt(children) => Re::Alt(children.iter().filter(|child| !is_never(child)).cloned().collect()),
+			_ => self
+		}
+	}
+
+	// Alternation with an empty alternative as an optional alternation of the
+	// others, |a and a| becoming a?. Groups would not always get the same
+	// spans, so the alternatives must have none.
+	fn empty_alternative_as_optional(self) -> Re {
+		let alternatives = match &self {
+			Re::Or(_, _) => {
+				let mut alternatives = Vec::new();
+				chain_operands(&Rc::from(self.clone()), true, &mut alternatives);
+				alternatives
+			},
+			Re::Alt(children) => children.clone(),
+			_ => return self
+		};
+		if self.group_count() > 0 || !alternatives.iter().any(|alternative| **alternative == Re::Empty) {
+			return self;
+		}
+
+		let others: Vec<Rc<Re>> = alternatives.into_iter().filter(|alternative| **alternative != Re::Empty).collect();
+		match others.as_slice() {
+			[] => Re::Empty,
+			_ if matches!(self, Re::Or(_, _)) => Re::Optional(Rc::from(Re::or_chain(&others))),
+			[single] => Re::Optional(single.clone()),
+			_ => Re::Optional(Rc::from(Re::Alt(others)))
+		}
+	}
+
+	// Alternation of single chars as one class, a|b|c becoming [a-c]. The
+	// alternatives all match one char so their order does not matter.
+	fn merge_char_alternatives(self) -> Re {
+		let alternatives = match &self {
+			Re::Or(_, _) => {
+				let mut alternatives = Vec::new();
+				chain_operands(&Rc::from(self.clone()), true, &mut alternatives);
+				alternatives
+			},
+			Re::Alt(children) if children.len() > 1 => children.clone(),
+			_ => return self
+		};
+
+		let mut ranges = Vec::new();
+		for alternative in &alternatives {
+			match alternative.as_ref() {
+				Re::Char(c) => ranges.push((*c, *c)),
+				Re::Class(class) if !class.negated => ranges.extend(class.ranges.iter().copied()),
+				_ => return self
+			}
+		}
+		Re::Class(CharClass::new(merge_ranges(ranges), false))
+	}
+
+	// Factor out an anchor starting or ending every alternative of the
+	// top-level alternation, ^a|^b becoming ^(?:a|b)
+	fn hoist_anchors(self) -> Re {
+		let alternatives = match &self {
+			Re::Or(_, _) => {
+				let mut alternatives = Vec::new();
+				chain_operands(&Rc::from(self.clone()), true, &mut alternatives);
+				alternatives
+			},
+			Re::Alt(children) if children.len() > 1 => children.clone(),
+			_ => return self
+		};
+		let rebuild = |items: Vec<Re>| {
+			let items: Vec<Rc<Re>> = items.into_iter().map(Rc::from).collect();
+			let reg = if matches!(self, Re::Or(_, _)) { Re::or_chain(&items) } else { Re::Alt(items) };
+			Rc::from(reg.simplify_node())
+		};
+
+		for anchor in [Re::Start, Re::TextStart] {
+			if let Some(rest) = alternatives.iter().map(|reg| strip_leading(reg, &anchor)).collect() {
+				return Re::And(Rc::from(anchor), rebuild(rest));
+			}
+		}
+		for anchor in [Re::End, Re::TextEnd] {
+			if let Some(rest) = alternatives.iter().map(|reg| strip_trailing(reg, &anchor)).collect() {
+				return Re::And(rebuild(rest), Rc::from(anchor));
+			}
+		}
+		self
+	}
+
+	// Rewrite rules applied to a node whose children are simplified
+	fn simplify_node(self) -> Re {
+		match &self {
+			// what must match a regexp matching nothing matches nothing, as
+			// long as no group is dropped with it, and what may skip it
+			// matches the empty string
+			Re::And(c1, c2) if (is_never(c1) || is_never(c2)) && self.group_count() == 0 => Re::never(),
+			Re::Concat(children) if children.iter().any(|child| is_never(child)) && self.group_count() == 0 => Re::never(),
+			Re::OneOrMore(c) | Re::Atomic(c) if is_never(c) => Re::never(),
+			Re::Repeat(c, a, _) if is_never(c) => if *a > 0 { Re::never() } else { Re::Empty },
+			Re::Kleen(c) | Re::Optional(c) if is_never(c) => Re::Empty,
+			Re::Or(_, _) | Re::Alt(_) => self.drop_never_alternatives().dedup_alternatives().empty_alternative_as_optional()
+				.merge_char_alternatives().hoist_anchors(),
+			// x?? and x*? are x? and x*, (x?)* is x*
+			Re::Optional(c) if matches!(**c, Re::Optional(_) | Re::Kleen(_)) => c.as_ref().clone(),
+			Re::Kleen(c) => match c.as_ref() {
+				Re::Optional(inner) => Re::Kleen(inner.clone()),
+				_ => self
+			},
+			// x{1,1} is x, x{0,0} is the empty string as long as dropping x
+			// does not remove a group. A repeat always has an upper bound,
+			// x{0,} and x{1,} being already read as x* and x+ by the parser.
+			Re::Repeat(c, 1, 1) => c.as_ref().clone(),
+			Re::Repeat(c, 0, 0) if c.group_count() == 0 => Re::Empty,
+			// an anchor right after the same one always holds, ^^ being ^
+			Re::And(c1, c2) if is_anchor(c1) && c1 == c2 => c1.as_ref().clone(),
+			Re::And(c1, c2) => match (c1.as_ref(), c2.as_ref()) {
+				(_, Re::And(next, _)) if is_anchor(c1) && c1 == next => c2.as_ref().clone(),
+				(Re::And(_, last), _) if is_anchor(c2) && last == c2 => c1.as_ref().clone(),
+				_ => self
+			},
+			Re::Concat(children) => {
+				let mut children = children.clone();
+				children.dedup_by(|next, last| is_anchor(last) && next == last);
+				Re::Concat(children)
+			},
+			_ => self
+		}
+	}
+}
+
+// The regexp matching nothing is an empty Alt, see Re::never
+fn is_never(reg: &Re) -> bool {
+	matches!(reg, Re::Alt(children) if children.is_empty())
+}
+
+pub(super) fn is_anchor(reg: &Re) -> bool {
+	matches!(reg, Re::Start | Re::End | Re::TextStart | Re::TextEnd)
+}
+
+// Sorted ranges with the overlapping and adjacent ones joined
+pub(super) fn merge_ranges(mut ranges: Vec<(char, char)>) -> Vec<(char, char)> {
+	ranges.sort_unstable();
+	let mut merged: Vec<(char, char)> = Vec::new();
+	for (low, high) in ranges {
+		match merged.last_mut() {
+			Some((_, last_high)) if low as u32 <= *last_high as u32 + 1 => *last_high = high.max(*last_high),
+			_ => merged.push((low, high))
+		}
+	}
+	merged
+}
+
+// What is left of a concatenation starting with the anchor once it is
+// removed, None if it does not start with it
+fn strip_leading(reg: &Re, anchor: &Re) -> Option<Re> {
+	match reg {
+		_ if reg == anchor => Some(Re::Empty),
+		Re::And(c1, c2) => strip_leading(c1, anchor).map(|rest| match rest {
+			Re::Empty => c2.as_ref().clone(),
+			rest => Re::And(Rc::from(rest), c2.clone())
+		}),
+		Re::Concat(children) => {
+			let (first, rest) = children.split_first()?;
+			let mut children = rest.to_vec();
+			match strip_leading(first, anchor)? {
+				Re::Empty => (),
+				first => children.insert(0, Rc::from(first))
+			}
+			Some(Re::Concat(children))
+		},
+		_ => None
+	}
+}
+
+fn strip_trailing(reg: &Re, anchor: &Re) -> Option<Re> {
+	match reg {
+		_ if reg == anchor => Some(Re::Empty),
+		Re::And(c1, c2) => strip_trailing(c2, anchor).map(|rest| match rest {
+			Re::Empty => c1.as_ref().clone(),
+			rest => Re::And(c1.clone(), Rc::from(rest))
+		}),
+		Re::Concat(children) => {
+			let (last, rest) = children.split_last()?;
+			let mut children = rest.to_vec();
+			match strip_trailing(last, anchor)? {
+				Re::Empty => (),
+				last => children.push(Rc::from(last))
+			}
+			Some(Re::Concat(children))
+		},
+		_ => None
+	}
+}
+
+fn dedup(items: Vec<Rc<Re>>) -> Vec<Rc<Re>> {
+	let mut unique: Vec<Rc<Re>> = Vec::new();
+	for item in items {
+		if !unique.contains(&item) {
+			unique.push(item);
+		}
+	}
+	unique
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::rc::Rc;
+	use alloc::vec;
+	use crate::regexp::Re;
+
+	fn optional(reg: Re) -> Re {
+		Re::Optional(Rc::from(reg))
+	}
+
+	#[test]
+	fn simplify_fixpoint() {
+		// the alternatives are only regrouped as b|a in the first pass
+		let reg = Re::parse_regexp("(?:$|b)|a").unwrap();
+		let simplified = reg.simplify();
+		assert!(simplified == Re::parse_regexp("$|[a-b]").unwrap());
+		assert!(simplified.simplify() == simplified);
+
+		for pattern in ["(?:(?:a|a)?)?", "(?:[^b-c]*)*|b|a{1,1}", "(a|b)|c"] {
+			let simplified = Re::parse_regexp(pattern).unwrap().simplify();
+			assert!(simplified.simplify() == simplified, "{pattern}");
+		}
+	}
+
+	#[test]
+	fn nested_optionals() {
+		let a = || Re::char('a');
+
+		assert!(optional(optional(a())).simplify() == optional(a()));
+		assert!(Re::kleen(optional(a())).simplify() == Re::kleen(a()));
+		assert!(optional(Re::kleen(a())).simplify() == Re::kleen(a()));
+		assert!(optional(optional(optional(Re::kleen(a())))).simplify() == Re::kleen(a()));
+
+		// the rules apply at any depth, but not through a capturing group
+		let reg = Re::and(Re::char('x'), optional(optional(a())));
+		assert!(reg.simplify() == Re::and(Re::char('x'), optional(a())));
+		let reg = Re::parse_regexp("(a?)*").unwrap();
+		assert!(reg.simplify() == reg);
+	}
+
+	#[test]
+	fn duplicate_alternatives() {
+		let reg = Re::parse_regexp("a|b|a").unwrap();
+		assert!(reg.dedup_alternatives() == Re::parse_regexp("a|b").unwrap());
+		assert!(reg.simplify() == Re::class(vec![('a', 'b')], false));
+
+		let a = || Re::char('a');
+		assert!(Re::kleen(Re::or(a(), a())).simplify() == Re::kleen(a()));
+		assert!(Re::parse_regexp("(a|a)*").unwrap().simplify() == Re::parse_regexp("(a)*").unwrap());
+		assert!(Re::parse_regexp("x(bc|d|bc)|x(bc|d)").unwrap().simplify() == Re::parse_regexp("x(bc|d)|x(bc|d)").unwrap());
+
+		let reg = Re::parse_regexp("c|a|b|a|c").unwrap().to_nary();
+		assert!(reg.dedup_alternatives() == Re::parse_regexp("c|a|b").unwrap().to_nary());
+		assert!(Re::parse_regexp("a|a").unwrap().to_nary().dedup_alternatives() == a());
+	}
+
+	#[test]
+	fn non_capturing_groups() {
+		let reg = Re::parse_regexp("(?:(?:ab))").unwrap();
+		assert!(reg.simplify() == Re::and(Re::char('a'), Re::char('b')));
+
+		let reg = Re::parse_regexp("(ab)").unwrap();
+		assert!(reg.simplify() == Re::group(Re::and(Re::char('a'), Re::char('b')), 1));
+		let reg = Re::parse_regexp("(?:x(?:(a)|b))(c)").unwrap();
+		// the capturing groups keep their numbers
+		let x_a_or_b = Re::and(Re::char('x'), Re::or(Re::group(Re::char('a'), 1), Re::char('b')));
+		assert!(reg.simplify() == Re::and(x_a_or_b, Re::group(Re::char('c'), 2)));
+	}
+
+	#[test]
+	fn trivial_repeats() {
+		let simplified = |pattern: &str| Re::parse_regexp(pattern).unwrap().simplify();
+
+		assert!(simplified("a{1,1}") == Re::char('a'));
+		assert!(simplified("a{0,0}") == Re::Empty);
+		assert!(simplified("xa{0,0}") == Re::and(Re::char('x'), Re::Empty));
+		assert!(simplified("(ab){1,1}") == Re::parse_regexp("(ab)").unwrap());
+		assert!(simplified("(a{1,1}){1,1}b") == Re::parse_regexp("(a)b").unwrap());
+
+		// the group must stay so that the captures keep their length
+		assert!(simplified("(a){0,0}") == Re::parse_regexp("(a){0,0}").unwrap());
+		assert!(simplified("a{2,2}") == Re::parse_regexp("a{2,2}").unwrap());
+
+		// no upper bound
+		assert!(simplified("a{0,}") == Re::kleen(Re::char('a')));
+		assert!(simplified("a{1,}") == Re::one_or_more(Re::char('a')));
+		assert!(simplified("(?:a{1,1}){0,}") == Re::kleen(Re::char('a')));
+	}
+
+	#[test]
+	fn char_alternatives() {
+		let simplified = |pattern: &str| Re::parse_regexp(pattern).unwrap().simplify();
+
+		let reg = simplified("a|b|c");
+		assert!(reg == Re::class(vec![('a', 'c')], false));
+		assert!(reg.is_match("b"));
+		assert!(simplified("a|c|x") == Re::class(vec![('a', 'a'), ('c', 'c'), ('x', 'x')], false));
+		assert!(simplified("[a-c]|d|[b-e]|x") == Re::class(vec![('a', 'e'), ('x', 'x')], false));
+		assert!(simplified("(x|y|z)+") == Re::parse_regexp("([x-z])+").unwrap());
+		assert!(Re::parse_regexp("c|a|b").unwrap().to_nary().simplify() == Re::class(vec![('a', 'c')], false));
+
+		// only when every alternative is a single char
+		assert!(simplified("a|bc") == Re::parse_regexp("a|bc").unwrap());
+		assert!(simplified("a|[^b]") == Re::parse_regexp("a|[^b]").unwrap());
+		assert!(simplified("a|.") == Re::parse_regexp("a|.").unwrap());
+	}
+
+	#[test]
+	fn never_propagation() {
+		let a = || Re::char('a');
+		let never = Re::never;
+
+		assert!(Re::and(a(), never()).simplify() == never());
+		assert!(Re::and(never(), a()).simplify() == never());
+		assert!(Re::Concat(vec![Rc::from(a()), Rc::from(never()), Rc::from(a())]).simplify() == never());
+		assert!(Re::one_or_more(never()).simplify() == never());
+		assert!(Re::repeat(never(), 2, 3).unwrap().simplify() == never());
+		assert!(Re::repeat(never(), 0, 3).unwrap().simplify() == Re::Empty);
+		assert!(Re::kleen(never()).simplify() == Re::Empty);
+		assert!(optional(never()).simplify() == Re::Empty);
+
+		assert!(Re::or(a(), never()).simplify() == a());
+		assert!(Re::or(never(), Re::or(a(), never())).simplify() == a());
+		assert!(Re::or(never(), never()).simplify() == never());
+		assert!(Re::Alt(vec![Rc::from(never()), Rc::from(a())]).simplify() == a());
+
+		// it goes up through the parents
+		let reg = Re::or(Re::char('b'), Re::and(a(), Re::one_or_more(never())));
+		assert!(reg.simplify() == Re::char('b'));
+		let reg = Re::and(a(), Re::kleen(never()));
+		assert!(reg.simplify() == Re::and(a(), Re::Empty));
+
+		// a group is kept even if it can never match
+		let reg = Re::and(Re::group(a(), 1), never());
+		assert!(reg.simplify() == reg);
+		assert!(!reg.is_match("a"));
+	}
+
+	#[test]
+	fn unreachable_alternatives() {
+		let a = || Rc::from(Re::char('a'));
+		let never = || Rc::from(Re::never());
+
+		assert!(Re::Alt(vec![a(), never()]).simplify() == Re::char('a'));
+		assert!(Re::Alt(vec![never(), a(), never()]).simplify() == Re::char('a'));
+		let group = Rc::from(Re::group(Re::char('b'), 1));
+		assert!(Re::Alt(vec![never(), group.clone()]).simplify() == *group);
+
+		let reg = Re::Alt(vec![a(), never(), Rc::from(Re::char('c'))]);
+		let simplified = reg.simplify();
+		assert!(simplified == Re::parse_regexp("a|c").unwrap().simplify());
+		for input in ["a", "c", "", "b"] {
+			assert_eq!(simplified.is_match(input), reg.is_match(input), "{input:?}");
+		}
+	}
+
+	#[test]
+	fn empty_alternatives() {
+		let a = || Re::char('a');
+		assert!(Re::or(Re::Empty, a()).simplify() == optional(a()));
+		assert!(Re::or(a(), Re::Empty).simplify() == optional(a()));
+		assert!(Re::or(Re::Empty, Re::Empty).simplify() == Re::Empty);
+
+		let reg = Re::or(Re::char('x'), Re::or(Re::Empty, Re::and(a(), a())));
+		assert!(reg.simplify() == Re::or(Re::char('x'), optional(Re::and(a(), a()))));
+		let reg = Re::Alt(vec![Rc::from(a()), Rc::from(Re::Empty), Rc::from(Re::char('b'))]);
+		assert!(reg.simplify() == optional(Re::class(vec![('a', 'b')], false)));
+
+		// a group could get another span
+		let reg = Re::or(Re::Empty, Re::group(Re::kleen(a()), 1));
+		assert!(reg.simplify() == reg);
+	}
+
+	#[test]
+	fn doubled_anchors() {
+		let simplified = Re::parse_regexp("^^a").unwrap().simplify();
+		assert!(simplified == Re::parse_regexp("^a").unwrap());
+		assert!(simplified.is_match("a"));
+		assert!(simplified.find("ba").is_none());
+
+		assert!(Re::parse_regexp("a$$").unwrap().simplify() == Re::parse_regexp("a$").unwrap());
+		assert!(Re::parse_regexp("\\A\\A\\z\\z").unwrap().simplify() == Re::parse_regexp("\\A\\z").unwrap());
+		assert!(Re::parse_regexp("^$").unwrap().simplify() == Re::parse_regexp("^$").unwrap());
+
+		let reg = Re::parse_regexp("a^^b$$c").unwrap().to_nary().simplify();
+		assert!(reg == Re::parse_regexp("a^b$c").unwrap().to_nary());
+	}
+
+	#[test]
+	fn hoisted_anchors() {
+		let simplified = |pattern: &str| Re::parse_regexp(pattern).unwrap().simplify();
+		let (a, b) = (|| Re::char('a'), || Re::char('b'));
+
+		let reg = simplified("^a|^bc");
+		assert!(reg == Re::and(Re::Start, Re::or(a(), Re::and(b(), Re::char('c')))));
+		for input in ["a", "bc", "abc", "bca", ""] {
+			assert_eq!(reg.is_match(input), Re::parse_regexp("^a|^bc").unwrap().is_match(input));
+			assert_eq!(reg.find(input), Re::parse_regexp("^a|^bc").unwrap().find(input));
+		}
+		// the alternatives left can then be merged into a class
+		assert!(simplified("^a|^b") == Re::and(Re::Start, Re::class(vec![('a', 'b')], false)));
+
+		assert!(simplified("ab$|b$") == Re::and(Re::or(Re::and(a(), b()), b()), Re::End));
+		assert!(simplified("^ab$|^b$") == Re::and(Re::Start, Re::and(Re::or(Re::and(a(), b()), b()), Re::End)));
+		assert!(simplified("\\Aa|\\Ab|\\Ac").to_regex_crate_pattern() == "\\A[a-c]");
+
+		// every alternative must have the anchor
+		assert!(simplified("^a|b") == Re::parse_regexp("^a|b").unwrap());
+		assert!(simplified("^a|\\Ab") == Re::parse_regexp("^a|\\Ab").unwrap());
+		let reg = Re::parse_regexp("^ab|^c").unwrap().to_nary();
+		assert!(reg.simplify().to_regex_crate_pattern() == "^(?:ab|c)");
+	}
+}
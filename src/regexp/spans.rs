@@ -0,0 +1,105 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use super::{Re, ReError, Span};
+
+// Span of a node of the pattern, its children being in the order of
+// Re::children
+pub(super) struct SpanTree {
+	pub(super) span: Span,
+	pub(super) children: Vec<SpanTree>
+}
+
+// Parsed regexp along with the source span of each of its nodes
+pub struct SpannedRe {
+	pub re: Re,
+	pub(super) spans: SpanTree
+}
+
+impl SpannedRe {
+	// Innermost node whose source span covers the byte offset
+	pub fn node_at(&self, byte: usize) -> Option<&Re> {
+		node_at(&self.re, &self.spans, byte)
+	}
+
+	// Regexp of new, the pattern old with the bytes of edit replaced. The
+	// spans are those of new, so an offset after the edit moves by the
+	// difference of length. This parses new again, the interface letting a
+	// later version reuse the subtrees the edit does not touch.
+	pub fn reparse(&self, old: &str, new: &str, edit: Range<usize>) -> Result<SpannedRe, ReError> {
+		debug_assert!(is_edit_of(old, new, &edit));
+		Re::parse_with_spans(new)
+	}
+}
+
+impl Re {
+	// Same as SpannedRe::reparse without the spans, giving back the regexp
+	// itself when the edit changed nothing
+	pub fn reparse(&self, old: &str, new: &str, edit: Range<usize>) -> Result<Re, ReError> {
+		debug_assert!(is_edit_of(old, new, &edit));
+		if old == new {
+			return Ok(self.clone());
+		}
+		Re::parse_regexp(new)
+	}
+}
+
+// Whether new is old with only the bytes of edit replaced
+fn is_edit_of(old: &str, new: &str, edit: &Range<usize>) -> bool {
+	edit.start <= edit.end && edit.end <= old.len()
+		&& new.len() >= edit.start + (old.len() - edit.end)
+		&& new.as_bytes().starts_with(&old.as_bytes()[..edit.start])
+		&& new.as_bytes().ends_with(&old.as_bytes()[edit.end..])
+}
+
+fn node_at<'a>(reg: &'a Re, spans: &SpanTree, byte: usize) -> Option<&'a Re> {
+	if byte < spans.span.start || byte >= spans.span.end {
+		return None;
+	}
+	reg.children().into_iter()
+		.zip(&spans.children)
+		.find_map(|(child, spans)| node_at(child, spans, byte))
+		.or(Some(reg))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::regexp::Re;
+
+	#[test]
+	fn reparse_after_edit() {
+		let old = "(a|b)*c";
+		let new = "(a|bd)*c";
+		let spanned = Re::parse_with_spans(old).unwrap();
+
+		let reparsed = spanned.reparse(old, new, 3..4).unwrap();
+		assert!(reparsed.re == Re::parse_regexp(new).unwrap());
+		// the spans are offsets of the new pattern
+		assert!(reparsed.node_at(4) == Some(&Re::Char('d')));
+		assert!(reparsed.node_at(7) == Some(&Re::Char('c')));
+
+		let reg = Re::parse_regexp(old).unwrap();
+		assert!(reg.reparse(old, new, 3..4).unwrap() == Re::parse_regexp(new).unwrap());
+		assert!(reg.reparse(old, old, 2..2).unwrap() == reg);
+		assert!(reg.reparse(old, "(a|b*c", 4..5).is_err());
+	}
+
+	#[test]
+	fn node_under_position() {
+		let spanned = Re::parse_with_spans("(a|b)").unwrap();
+		assert!(spanned.node_at(2) == Some(&Re::parse_regexp("a|b").unwrap()));
+		assert!(spanned.node_at(1) == Some(&Re::Char('a')));
+		assert!(spanned.node_at(0) == Some(&spanned.re));
+		assert!(spanned.node_at(5).is_none());
+
+		let spanned = Re::parse_with_spans("x[a-c]{2,3}y").unwrap();
+		assert!(spanned.node_at(3) == Some(&Re::parse_regexp("[a-c]").unwrap()));
+		assert!(spanned.node_at(7) == Some(&Re::parse_regexp("[a-c]{2,3}").unwrap()));
+		assert!(spanned.node_at(11) == Some(&Re::Char('y')));
+
+		// a non-capturing group is spanned by its body
+		let spanned = Re::parse_with_spans("(?:ab)c").unwrap();
+		assert!(spanned.node_at(0) == Some(&Re::parse_regexp("ab").unwrap()));
+		assert!(spanned.node_at(4) == Some(&Re::Char('b')));
+		assert!(spanned.node_at(6) == Some(&Re::Char('c')));
+	}
+}
@@ -0,0 +1,506 @@
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use super::{CharClass, Re};
+
+impl Re {
+	// Collapse chains of binary And and Or nodes into single Concat and Alt
+	// nodes, whatever way the chains are nested
+	pub fn to_nary(&self) -> Re {
+		match self {
+			Re::And(_, _) | Re::Concat(_) => {
+				let mut children = Vec::new();
+				self.collect_concat(&mut children);
+				Re::Concat(children)
+			},
+			Re::Or(_, _) | Re::Alt(_) => {
+				let mut children = Vec::new();
+				self.collect_alt(&mut children);
+				Re::Alt(children)
+			},
+			Re::Kleen(c) => Re::Kleen(Rc::from(c.to_nary())),
+			Re::OneOrMore(c) => Re::OneOrMore(Rc::from(c.to_nary())),
+			Re::Optional(c) => Re::Optional(Rc::from(c.to_nary())),
+			Re::Repeat(c, a, b) => Re::Repeat(Rc::from(c.to_nary()), *a, *b),
+			Re::Group(c, index) => Re::Group(Rc::from(c.to_nary()), *index),
+			Re::NamedGroup(c, index, name) => Re::NamedGroup(Rc::from(c.to_nary()), *index, name.clone()),
+			Re::Atomic(c) => Re::Atomic(Rc::from(c.to_nary())),
+			_ => self.clone()
+		}
+	}
+
+	// Factor the longest literal prefix shared by adjacent alternatives of a
+	// top-level alternation, abc|abd|x becoming ab(c|d)|x. Alternatives are
+	// only factored when something is left of each of them after the prefix.
+	pub fn factor_common_prefix(&self) -> Re {
+		let mut alternatives = Vec::new();
+		chain_operands(&Rc::from(self.clone()), true, &mut alternatives);
+		if alternatives.len() < 2 {
+			return self.clone();
+		}
+
+		let sequences: Vec<Vec<Rc<Re>>> = alternatives.iter()
+			.map(|alternative| {
+				let mut sequence = Vec::new();
+				chain_operands(alternative, false, &mut sequence);
+				sequence
+			})
+			.collect();
+
+		let mut factored = Vec::new();
+		let mut i = 0;
+		while i < sequences.len() {
+			let first = &sequences[i];
+			let mut end = i+1;
+			if first.len() > 1 && matches!(*first[0], Re::Char(_)) {
+				while end < sequences.len() && sequences[end].len() > 1 && sequences[end][0] == first[0] {
+					end += 1;
+				}
+			}
+
+			if end - i == 1 {
+				factored.push(alternatives[i].clone());
+			}
+			else {
+				factored.push(Rc::from(factor_group(&sequences[i..end])));
+			}
+			i = end;
+		}
+
+		Re::or_chain(&factored)
+	}
+
+	// Same as factor_common_prefix with the literal suffix shared by adjacent
+	// alternatives, abc|xbc|y becoming (?:a|x)bc|y
+	pub fn factor_common_suffix(&self) -> Re {
+		let mut alternatives = Vec::new();
+		chain_operands(&Rc::from(self.clone()), true, &mut alternatives);
+		if alternatives.len() < 2 {
+			return self.clone();
+		}
+
+		let sequences: Vec<Vec<Rc<Re>>> = alternatives.iter()
+			.map(|alternative| {
+				let mut sequence = Vec::new();
+				chain_operands(alternative, false, &mut sequence);
+				sequence
+			})
+			.collect();
+
+		let mut factored = Vec::new();
+		let mut i = 0;
+		while i < sequences.len() {
+			let last = sequences[i].last().unwrap();
+			let mut end = i+1;
+			if sequences[i].len() > 1 && matches!(**last, Re::Char(_)) {
+				while end < sequences.len() && sequences[end].len() > 1 && sequences[end].last() == Some(last) {
+					end += 1;
+				}
+			}
+
+			if end - i == 1 {
+				factored.push(alternatives[i].clone());
+			}
+			else {
+				factored.push(Rc::from(factor_suffix_group(&sequences[i..end])));
+			}
+			i = end;
+		}
+
+		Re::or_chain(&factored)
+	}
+
+	// Case-insensitive version of the regexp, every ASCII letter, alone or in
+	// a class, matching both its lowercase and uppercase forms
+	pub fn case_fold(&self) -> Re {
+		self.map_leaves(&mut |leaf| match leaf {
+			Re::Char(c) if c.is_ascii_alphabetic() => Re::Class(CharClass::new(
+				vec![(c.to_ascii_uppercase(), c.to_ascii_uppercase()), (c.to_ascii_lowercase(), c.to_ascii_lowercase())],
+				false
+			)),
+			Re::Class(class) => Re::Class(fold_class(class)),
+			_ => leaf.clone()
+		})
+	}
+
+	// Same regexp with f applied to every char and to the ends of the class
+	// ranges, a range whose mapped ends are out of order being reversed
+	pub fn map_chars(&self, f: impl Fn(char) -> char) -> Re {
+		self.map_leaves(&mut |leaf| match leaf {
+			Re::Char(c) => Re::Char(f(*c)),
+			Re::Class(class) => Re::Class(CharClass::new(
+				class.ranges.iter()
+					.map(|(low, high)| {
+						let (low, high) = (f(*low), f(*high));
+						(low.min(high), low.max(high))
+					})
+					.collect(),
+				class.negated
+			)),
+			_ => leaf.clone()
+		})
+	}
+
+	// Merge adjacent repetitions of equal subexpressions in concatenations
+	// by summing their bounds, a{2,3}a{1,2} becoming a{3,5} and a*a* becoming
+	// a*. Repetitions whose sum has no form here, like a{2,2}a+, are kept.
+	pub fn combine_repeats(&self) -> Re {
+		let combine = |c: &Rc<Re>| Rc::from(c.combine_repeats());
+		match self {
+			Re::And(_, _) => {
+				let mut items = Vec::new();
+				chain_operands(&Rc::from(self.clone()), false, &mut items);
+				Re::and_chain(&merge_repeats(items.iter().map(combine)))
+			},
+			Re::Concat(children) => Re::Concat(merge_repeats(children.iter().map(combine))),
+			Re::Or(c1, c2) => Re::Or(combine(c1), combine(c2)),
+			Re::Alt(children) => Re::Alt(children.iter().map(combine).collect()),
+			Re::Kleen(c) => Re::Kleen(combine(c)),
+			Re::OneOrMore(c) => Re::OneOrMore(combine(c)),
+			Re::Optional(c) => Re::Optional(combine(c)),
+			Re::Repeat(c, a, b) => Re::Repeat(combine(c), *a, *b),
+			Re::Group(c, index) => Re::Group(combine(c), *index),
+			Re::NamedGroup(c, index, name) => Re::NamedGroup(combine(c), *index, name.clone()),
+			Re::Atomic(c) => Re::Atomic(combine(c)),
+			_ => self.clone()
+		}
+	}
+
+	// Rebuild the tree bottom-up, each node being replaced by the result of
+	// f once its children are rebuilt
+	pub fn map(&self, f: impl Fn(Re) -> Re) -> Re {
+		self.map_nodes(&f)
+	}
+
+	fn map_nodes(&self, f: &impl Fn(Re) -> Re) -> Re {
+		let map = |c: &Rc<Re>| Rc::from(c.map_nodes(f));
+		let reg = match self {
+			Re::Or(c1, c2) => Re::Or(map(c1), map(c2)),
+			Re::And(c1, c2) => Re::And(map(c1), map(c2)),
+			Re::Kleen(c) => Re::Kleen(map(c)),
+			Re::OneOrMore(c) => Re::OneOrMore(map(c)),
+			Re::Optional(c) => Re::Optional(map(c)),
+			Re::Repeat(c, a, b) => Re::Repeat(map(c), *a, *b),
+			Re::Group(c, index) => Re::Group(map(c), *index),
+			Re::NamedGroup(c, index, name) => Re::NamedGroup(map(c), *index, name.clone()),
+			Re::Atomic(c) => Re::Atomic(map(c)),
+			Re::Concat(children) => Re::Concat(children.iter().map(map).collect()),
+			Re::Alt(children) => Re::Alt(children.iter().map(map).collect()),
+			Re::Empty | Re::Char(_) | Re::AnyChar | Re::Start | Re::End | Re::TextStart | Re::TextEnd | Re::Class(_) => self.clone()
+		};
+		f(reg)
+	}
+
+	// Rebuild the tree with the same operators, replacing each node without
+	// children by the result of f
+	fn map_leaves(&self, f: &mut impl FnMut(&Re) -> Re) -> Re {
+		let map = |c: &Rc<Re>, f: &mut _| Rc::from(c.map_leaves(f));
+		match self {
+			Re::Or(c1, c2) => Re::Or(map(c1, f), map(c2, f)),
+			Re::And(c1, c2) => Re::And(map(c1, f), map(c2, f)),
+			Re::Kleen(c) => Re::Kleen(map(c, f)),
+			Re::OneOrMore(c) => Re::OneOrMore(map(c, f)),
+			Re::Optional(c) => Re::Optional(map(c, f)),
+			Re::Repeat(c, a, b) => Re::Repeat(map(c, f), *a, *b),
+			Re::Group(c, index) => Re::Group(map(c, f), *index),
+			Re::NamedGroup(c, index, name) => Re::NamedGroup(map(c, f), *index, name.clone()),
+			Re::Atomic(c) => Re::Atomic(map(c, f)),
+			Re::Concat(children) => Re::Concat(children.iter().map(|c| map(c, f)).collect()),
+			Re::Alt(children) => Re::Alt(children.iter().map(|c| map(c, f)).collect()),
+			_ => f(self)
+		}
+	}
+
+	// Right folded And chain of the items, which must not be empty
+	pub(super) fn and_chain(items: &[Rc<Re>]) -> Re {
+		match items.split_last() {
+			None => unreachable!(),
+			Some((last, rest)) => rest.iter().rev()
+				.fold(last.as_ref().clone(), |right, left| Re::And(left.clone(), Rc::from(right)))
+		}
+	}
+
+	// Right folded Or chain of the items, which must not be empty
+	pub(super) fn or_chain(items: &[Rc<Re>]) -> Re {
+		match items.split_last() {
+			None => unreachable!(),
+			Some((last, rest)) => rest.iter().rev()
+				.fold(last.as_ref().clone(), |right, left| Re::Or(left.clone(), Rc::from(right)))
+		}
+	}
+
+	fn collect_concat(&self, children: &mut Vec<Rc<Re>>) {
+		match self {
+			Re::And(c1, c2) => {
+				c1.collect_concat(children);
+				c2.collect_concat(children);
+			},
+			Re::Concat(list) => list.iter().for_each(|child| child.collect_concat(children)),
+			_ => children.push(Rc::from(self.to_nary()))
+		}
+	}
+
+	fn collect_alt(&self, children: &mut Vec<Rc<Re>>) {
+		match self {
+			Re::Or(c1, c2) => {
+				c1.collect_alt(children);
+				c2.collect_alt(children);
+			},
+			Re::Alt(list) => list.iter().for_each(|child| child.collect_alt(children)),
+			_ => children.push(Rc::from(self.to_nary()))
+		}
+	}
+}
+
+// Operands, left to right and left untouched, of a chain of Or nodes or of
+// And nodes
+pub(super) fn chain_operands(reg: &Rc<Re>, alternation: bool, items: &mut Vec<Rc<Re>>) {
+	match (reg.as_ref(), alternation) {
+		(Re::Or(c1, c2), true) | (Re::And(c1, c2), false) => {
+			chain_operands(c1, alternation, items);
+			chain_operands(c2, alternation, items);
+		},
+		_ => items.push(reg.clone())
+	}
+}
+
+// Operand and bounds of a repetition, None for an unbounded maximum
+fn repetition(reg: &Re) -> Option<(&Rc<Re>, usize, Option<usize>)> {
+	match reg {
+		Re::Kleen(c) => Some((c, 0, None)),
+		Re::OneOrMore(c) => Some((c, 1, None)),
+		Re::Optional(c) => Some((c, 0, Some(1))),
+		Re::Repeat(c, a, b) => Some((c, *a, Some(*b))),
+		_ => None
+	}
+}
+
+// Sequence of items with each pair of adjacent repetitions of the same
+// operand merged, when the merged bounds can be represented
+fn merge_repeats(items: impl Iterator<Item = Rc<Re>>) -> Vec<Rc<Re>> {
+	let mut merged: Vec<Rc<Re>> = Vec::new();
+	for item in items {
+		let combined = merged.last()
+			.and_then(|last| repetition(last).zip(repetition(&item)))
+			.filter(|((c1, _, _), (c2, _, _))| c1 == c2)
+			.and_then(|((c, a1, b1), (_, a2, b2))| {
+				let min = a1.checked_add(a2)?;
+				match (min, b1.zip(b2)) {
+					(_, Some((b1, b2))) => Some(Re::Repeat(c.clone(), min, b1.checked_add(b2)?)),
+					(0, None) => Some(Re::Kleen(c.clone())),
+					(1, None) => Some(Re::OneOrMore(c.clone())),
+					_ => None
+				}
+			});
+
+		match combined {
+			Some(reg) => *merged.last_mut().unwrap() = Rc::from(reg),
+			None => merged.push(item)
+		}
+	}
+	merged
+}
+
+// Ranges of the class with the other case of its ASCII letters added
+fn fold_class(class: &CharClass) -> CharClass {
+	let mut ranges = class.ranges.clone();
+	for (low, high) in &class.ranges {
+		for (from, to) in [('a', 'A'), ('A', 'a')] {
+			let start = (*low).max(from);
+			let end = (*high).min((from as u8 + 25) as char);
+			if start <= end {
+				let shift = |c: char| (c as u8 - from as u8 + to as u8) as char;
+				ranges.push((shift(start), shift(end)));
+			}
+		}
+	}
+	CharClass::new(ranges, class.negated)
+}
+
+// Factor sequences starting with the same char and at least two items
+// long, keeping at least one item of each after the shared prefix
+fn factor_group(sequences: &[Vec<Rc<Re>>]) -> Re {
+	let shortest = sequences.iter().map(|sequence| sequence.len()).min().unwrap();
+	let prefix_len = (0..shortest-1)
+		.take_while(|k| {
+			matches!(*sequences[0][*k], Re::Char(_))
+				&& sequences.iter().all(|sequence| sequence[*k] == sequences[0][*k])
+		})
+		.count();
+
+	let rests: Vec<Rc<Re>> = sequences.iter()
+		.map(|sequence| Rc::from(Re::and_chain(&sequence[prefix_len..])))
+		.collect();
+
+	let mut items = sequences[0][..prefix_len].to_vec();
+	items.push(Rc::from(Re::or_chain(&rests)));
+	Re::and_chain(&items)
+}
+
+// Factor sequences ending with the same char and at least two items long,
+// keeping at least one item of each before the shared suffix
+fn factor_suffix_group(sequences: &[Vec<Rc<Re>>]) -> Re {
+	let shortest = sequences.iter().map(|sequence| sequence.len()).min().unwrap();
+	let item = |sequence: &[Rc<Re>], k: usize| sequence[sequence.len()-1-k].clone();
+	let suffix_len = (0..shortest-1)
+		.take_while(|k| {
+			matches!(*item(&sequences[0], *k), Re::Char(_))
+				&& sequences.iter().all(|sequence| item(sequence, *k) == item(&sequences[0], *k))
+		})
+		.count();
+
+	let rests: Vec<Rc<Re>> = sequences.iter()
+		.map(|sequence| Rc::from(Re::and_chain(&sequence[..sequence.len()-suffix_len])))
+		.collect();
+
+	let mut items = vec![Rc::from(Re::or_chain(&rests))];
+	items.extend_from_slice(&sequences[0][sequences[0].len()-suffix_len..]);
+	Re::and_chain(&items)
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::rc::Rc;
+	use alloc::vec::Vec;
+	use crate::regexp::Re;
+
+	fn chars(string: &str) -> Vec<Rc<Re>> {
+		string.chars().map(|c| Rc::from(Re::Char(c))).collect()
+	}
+
+	#[test]
+	fn nary_alternation() {
+		let reg = Re::parse_regexp("a|b|c").unwrap().to_nary();
+		assert!(reg == Re::Alt(chars("abc")));
+	}
+
+	#[test]
+	fn nary_nesting() {
+		let reg = Re::parse_regexp("abc(d|e)*").unwrap().to_nary();
+		let mut children = chars("abc");
+		children.push(Rc::from(Re::Kleen(Rc::from(Re::Group(Rc::from(Re::Alt(chars("de"))), 1)))));
+		assert!(reg == Re::Concat(children));
+
+		// left nested chains are flattened as well
+		let ab = Re::And(Rc::from(Re::Char('a')), Rc::from(Re::Char('b')));
+		let reg = Re::And(Rc::from(ab), Rc::from(Re::Char('c')));
+		assert!(reg.to_nary() == Re::Concat(chars("abc")));
+		assert_eq!(reg.to_nary().to_postfix(), reg.to_postfix());
+	}
+
+	#[test]
+	fn prefix_factoring() {
+		let reg = Re::parse_regexp("abc|abd").unwrap().factor_common_prefix();
+		let expected = Re::and(Re::char('a'), Re::and(Re::char('b'), Re::or(Re::char('c'), Re::char('d'))));
+		assert!(reg == expected);
+
+		for input in ["abc", "abd", "ab", "abe"] {
+			assert_eq!(reg.is_match(input), Re::parse_regexp("abc|abd").unwrap().is_match(input));
+		}
+	}
+
+	#[test]
+	fn partial_prefix_factoring() {
+		// only the alternatives sharing a prefix are factored
+		let reg = Re::parse_regexp("x|abc|abd|y").unwrap().factor_common_prefix();
+		let factored = Re::and(Re::char('a'), Re::and(Re::char('b'), Re::or(Re::char('c'), Re::char('d'))));
+		assert!(reg == Re::or(Re::char('x'), Re::or(factored, Re::char('y'))));
+
+		// a must stay a whole alternative, nothing would be left of it
+		let reg = Re::parse_regexp("a|ab|ac").unwrap().factor_common_prefix();
+		assert!(reg == Re::or(Re::char('a'), Re::and(Re::char('a'), Re::or(Re::char('b'), Re::char('c')))));
+
+		let reg = Re::parse_regexp("abc|xbc").unwrap();
+		assert!(reg.factor_common_prefix() == reg);
+		let reg = Re::parse_regexp("ab*").unwrap();
+		assert!(reg.factor_common_prefix() == reg);
+	}
+
+	#[test]
+	fn suffix_factoring() {
+		let reg = Re::parse_regexp("abc|xbc").unwrap().factor_common_suffix();
+		let expected = Re::and(Re::or(Re::char('a'), Re::char('x')), Re::and(Re::char('b'), Re::char('c')));
+		assert!(reg == expected);
+		for input in ["abc", "xbc", "bc", "ybc", "ab"] {
+			assert_eq!(reg.is_match(input), Re::parse_regexp("abc|xbc").unwrap().is_match(input));
+		}
+
+		// only the adjacent alternatives sharing a suffix are factored, each
+		// keeping something in front of it
+		let reg = Re::parse_regexp("y|abc|xdc|c").unwrap().factor_common_suffix();
+		let factored = Re::and(Re::or(Re::and(Re::char('a'), Re::char('b')), Re::and(Re::char('x'), Re::char('d'))), Re::char('c'));
+		assert!(reg == Re::or(Re::char('y'), Re::or(factored, Re::char('c'))));
+
+		let reg = Re::parse_regexp("abc|abd").unwrap();
+		assert!(reg.factor_common_suffix() == reg);
+	}
+
+	#[test]
+	fn bottom_up_map() {
+		let reg = Re::parse_regexp("(?P<x>a|[b-c])*\\d{1,2}$").unwrap();
+		assert!(reg.map(|node| node) == reg);
+
+		let doubled = Re::parse_regexp("a(b|c)").unwrap().map(|node| match node {
+			Re::Char(_) => Re::repeat(node, 2, 2).unwrap(),
+			_ => node
+		});
+		assert!(doubled == Re::parse_regexp("a{2,2}(b{2,2}|c{2,2})").unwrap());
+		assert!(doubled.is_match("aacc"));
+		assert!(!doubled.is_match("ac"));
+
+		// the parents see their rewritten children
+		let reg = Re::parse_regexp("a*").unwrap().map(|node| match node {
+			Re::Kleen(c) if *c == Re::char('b') => Re::Empty,
+			Re::Char(_) => Re::char('b'),
+			_ => node
+		});
+		assert!(reg == Re::Empty);
+	}
+
+	#[test]
+	fn case_folding() {
+		let reg = Re::parse_regexp("abc").unwrap().case_fold();
+		for input in ["abc", "ABC", "aBc"] {
+			assert!(reg.is_match(input));
+		}
+		assert!(!reg.is_match("abd"));
+
+		let reg = Re::parse_regexp("x-1(y|[c-e_])+").unwrap().case_fold();
+		assert!(reg.is_match("X-1yDe_"));
+		assert!(!reg.is_match("x_1y"));
+
+		let reg = Re::parse_regexp("[^a-b]").unwrap().case_fold();
+		assert!(!reg.is_match("B"));
+		assert!(reg.is_match("c"));
+	}
+
+	#[test]
+	fn repeat_combining() {
+		let combined = |pattern: &str| Re::parse_regexp(pattern).unwrap().combine_repeats();
+
+		assert!(combined("a{2,3}a{1,2}") == Re::parse_regexp("a{3,5}").unwrap());
+		assert!(combined("a*a*") == Re::parse_regexp("a*").unwrap());
+		assert!(combined("xa*a+y") == Re::parse_regexp("xa+y").unwrap());
+		assert!(combined("b|c{1,1}c*c{0,2}") == Re::parse_regexp("b|c+").unwrap());
+
+		// bounds that cannot be written or operands that differ are kept
+		for pattern in ["a{2,2}a+", "a*b*", "(a)*(a)*", "a*a"] {
+			assert!(combined(pattern) == Re::parse_regexp(pattern).unwrap(), "{pattern}");
+		}
+	}
+
+	#[test]
+	fn char_mapping() {
+		let reg = Re::parse_regexp("abc").unwrap().map_chars(|c| c.to_ascii_uppercase());
+		assert!(reg.is_match("ABC"));
+		assert!(!reg.is_match("abc"));
+
+		let reg = Re::parse_regexp("(x[a-c]|\\d)*.").unwrap();
+		let mapped = reg.map_chars(|c| c.to_ascii_uppercase());
+		assert!(mapped == Re::parse_regexp("(X[A-C]|\\d)*.").unwrap());
+
+		// ranges stay ordered whatever the mapping does
+		let reversed = Re::parse_regexp("[a-c]").unwrap().map_chars(|c| (b'z' - (c as u8 - b'a')) as char);
+		assert!(reversed == Re::parse_regexp("[x-z]").unwrap());
+	}
+}